@@ -0,0 +1,403 @@
+pub const USAGE: &str = "Usage: rtop [OPTIONS]
+
+Options:
+  -c, --config <PATH>     Use the config file at PATH
+      --check-config      Check the config and exit
+      --list-widgets      List the built-in and plugin widgets and exit
+      --page <N>          Start on page N
+      --refresh-ms <MS>   Update the widgets every MS milliseconds (default: 333)
+      --snapshot          Print the pages as text and exit, or only the one of --page
+      --ticks <N>         Update the widgets N times before the snapshot (default: 2)
+      --size <COLSxROWS>  Size of the pages of the snapshot (default: 80x24)
+      --ansi              Keep the colors and styles in the snapshot
+      --export <FORMAT>   Print the metrics as json or ndjson on each refresh and no interface
+      --output <PATH>     Append the exported metrics to the file at PATH
+      --count <N>         Exit after exporting the metrics N times
+      --listen <ADDR>     Serve the metrics in the OpenMetrics format at http://ADDR/metrics
+      --headless          Only serve the metrics of --listen, without the interface
+      --record <PATH>     Record what the built-in widgets show to the file at PATH
+      --replay <PATH>     Show a recording of --record instead of the live system
+  -h, --help              Print this help and exit
+  -V, --version           Print the version and exit";
+
+const OPTIONS_WITH_VALUE: [&str; 11] = [
+    "--config",
+    "--page",
+    "--refresh-ms",
+    "--ticks",
+    "--size",
+    "--export",
+    "--output",
+    "--count",
+    "--listen",
+    "--record",
+    "--replay",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Pretty-printed objects
+    Json,
+    /// One object per line
+    Ndjson,
+}
+
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct Args {
+    pub config: Option<String>,
+    pub check_config: bool,
+    pub list_widgets: bool,
+    /// Page to start on, starting at 1
+    pub page: Option<usize>,
+    pub refresh_ms: Option<u64>,
+    pub snapshot: bool,
+    /// Updates of the widgets before the snapshot
+    pub ticks: Option<u32>,
+    /// Columns and rows of the snapshot
+    pub size: Option<(i32, i32)>,
+    pub ansi: bool,
+    pub export: Option<ExportFormat>,
+    pub output: Option<String>,
+    /// Number of exports before exiting
+    pub count: Option<u64>,
+    /// Address of the OpenMetrics server
+    pub listen: Option<std::net::SocketAddr>,
+    pub headless: bool,
+    /// Recording written while the interface runs
+    pub record: Option<String>,
+    /// Recording shown instead of the live system
+    pub replay: Option<String>,
+    pub help: bool,
+    pub version: bool,
+}
+
+impl Args {
+    /// Parse the command line arguments, without the program name
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Values can be given as `--option value` or `--option=value`
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if name.starts_with("--") => {
+                    (String::from(name), Some(String::from(value)))
+                }
+                _ => (arg, None),
+            };
+            if inline_value.is_some() && !OPTIONS_WITH_VALUE.contains(&name.as_str()) {
+                return Err(format!("{} does not take a value", name));
+            }
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{} requires a value", name))
+            };
+
+            match name.as_str() {
+                "-c" | "--config" => parsed.config = Some(value()?),
+                "--check-config" => parsed.check_config = true,
+                "--list-widgets" => parsed.list_widgets = true,
+                "--page" => {
+                    let page = value()?;
+                    parsed.page = match page.parse() {
+                        Ok(page) if page > 0 => Some(page),
+                        _ => return Err(format!("invalid page number `{}`", page)),
+                    };
+                }
+                "--refresh-ms" => {
+                    let refresh_ms = value()?;
+                    parsed.refresh_ms = match refresh_ms.parse() {
+                        Ok(refresh_ms) if refresh_ms > 0 => Some(refresh_ms),
+                        _ => return Err(format!("invalid refresh interval `{}`", refresh_ms)),
+                    };
+                }
+                "--snapshot" => parsed.snapshot = true,
+                "--ticks" => {
+                    let ticks = value()?;
+                    parsed.ticks = match ticks.parse() {
+                        Ok(ticks) if ticks > 0 => Some(ticks),
+                        _ => return Err(format!("invalid number of ticks `{}`", ticks)),
+                    };
+                }
+                "--size" => {
+                    let size = value()?;
+                    let parsed_size = size.split_once('x').and_then(|(columns, rows)| {
+                        Some((columns.parse().ok()?, rows.parse().ok()?))
+                    });
+                    parsed.size = match parsed_size {
+                        Some((columns, rows)) if columns > 0 && rows > 0 => Some((columns, rows)),
+                        _ => return Err(format!("invalid size `{}`, expected COLSxROWS", size)),
+                    };
+                }
+                "--ansi" => parsed.ansi = true,
+                "--export" => {
+                    parsed.export = match value()?.as_str() {
+                        "json" => Some(ExportFormat::Json),
+                        "ndjson" => Some(ExportFormat::Ndjson),
+                        format => {
+                            return Err(format!(
+                                "unknown export format `{}`, expected `json` or `ndjson`",
+                                format
+                            ))
+                        }
+                    };
+                }
+                "--output" => parsed.output = Some(value()?),
+                "--count" => {
+                    let count = value()?;
+                    parsed.count = match count.parse() {
+                        Ok(count) if count > 0 => Some(count),
+                        _ => return Err(format!("invalid count `{}`", count)),
+                    };
+                }
+                "--listen" => {
+                    let address = value()?;
+                    parsed.listen = match address.parse() {
+                        Ok(address) => Some(address),
+                        _ => {
+                            return Err(format!("invalid address `{}`, expected IP:PORT", address))
+                        }
+                    };
+                }
+                "--headless" => parsed.headless = true,
+                "--record" => parsed.record = Some(value()?),
+                "--replay" => parsed.replay = Some(value()?),
+                "-h" | "--help" => parsed.help = true,
+                "-V" | "--version" => parsed.version = true,
+                _ => return Err(format!("unknown option `{}`", name)),
+            }
+        }
+
+        if parsed.snapshot && parsed.export.is_some() {
+            return Err(String::from(
+                "--snapshot and --export cannot be used together",
+            ));
+        }
+        if parsed.snapshot && parsed.listen.is_some() {
+            return Err(String::from(
+                "--snapshot and --listen cannot be used together",
+            ));
+        }
+        if parsed.headless && parsed.listen.is_none() {
+            return Err(String::from("--headless can only be used with --listen"));
+        }
+        if parsed.headless && parsed.export.is_some() {
+            return Err(String::from(
+                "--headless and --export cannot be used together",
+            ));
+        }
+        if parsed.listen.is_some() && parsed.check_config {
+            return Err(String::from("--listen cannot be used with --check-config"));
+        }
+        // Without the interface, the config is not read
+        if parsed.headless && parsed.config.is_some() {
+            return Err(String::from("--headless cannot be used with --config"));
+        }
+        if parsed.export.is_some() {
+            // The metrics do not depend on the config, which is not read
+            let config_options = [
+                ("--config", parsed.config.is_some()),
+                ("--check-config", parsed.check_config),
+            ];
+            if let Some((name, _)) = config_options.iter().find(|(_, given)| *given) {
+                return Err(format!("--export cannot be used with {}", name));
+            }
+        }
+        if parsed.record.is_some() && parsed.replay.is_some() {
+            return Err(String::from(
+                "--record and --replay cannot be used together",
+            ));
+        }
+        // Recordings are made and replayed by the interface
+        let interface_options = [
+            ("--record", parsed.record.is_some()),
+            ("--replay", parsed.replay.is_some()),
+        ];
+        let no_interface_options = [
+            ("--snapshot", parsed.snapshot),
+            ("--export", parsed.export.is_some()),
+            ("--headless", parsed.headless),
+        ];
+        for (name, _) in interface_options.iter().filter(|(_, given)| *given) {
+            if let Some((other, _)) = no_interface_options.iter().find(|(_, given)| *given) {
+                return Err(format!("{} and {} cannot be used together", name, other));
+            }
+        }
+        if parsed.export.is_none() {
+            let export_options = [
+                ("--output", parsed.output.is_some()),
+                ("--count", parsed.count.is_some()),
+            ];
+            if let Some((name, _)) = export_options.iter().find(|(_, given)| *given) {
+                return Err(format!("{} can only be used with --export", name));
+            }
+        }
+        if !parsed.snapshot {
+            let snapshot_options = [
+                ("--ticks", parsed.ticks.is_some()),
+                ("--size", parsed.size.is_some()),
+                ("--ansi", parsed.ansi),
+            ];
+            if let Some((name, _)) = snapshot_options.iter().find(|(_, given)| *given) {
+                return Err(format!("{} can only be used with --snapshot", name));
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().copied().map(String::from))
+    }
+
+    #[test]
+    fn values_separate_or_inline() {
+        let args = parse(&[
+            "-c",
+            "a.json",
+            "--page=2",
+            "--refresh-ms",
+            "500",
+            "--snapshot",
+            "--size=100x30",
+            "--ticks",
+            "3",
+        ])
+        .unwrap();
+        assert_eq!(
+            args,
+            Args {
+                config: Some(String::from("a.json")),
+                page: Some(2),
+                refresh_ms: Some(500),
+                snapshot: true,
+                size: Some((100, 30)),
+                ticks: Some(3),
+                ..Args::default()
+            }
+        );
+        assert_eq!(
+            parse(&["--config=b=c.json"]).unwrap().config.as_deref(),
+            Some("b=c.json")
+        );
+        assert_eq!(
+            parse(&["--listen=127.0.0.1:9184", "--headless"])
+                .unwrap()
+                .listen,
+            Some(std::net::SocketAddr::from(([127, 0, 0, 1], 9184)))
+        );
+    }
+
+    #[test]
+    fn missing_and_invalid_values() {
+        assert_eq!(
+            parse(&["--page"]),
+            Err(String::from("--page requires a value"))
+        );
+        assert_eq!(
+            parse(&["--check-config=yes"]),
+            Err(String::from("--check-config does not take a value"))
+        );
+        assert_eq!(
+            parse(&["--page", "0"]),
+            Err(String::from("invalid page number `0`"))
+        );
+        assert_eq!(
+            parse(&["--refresh-ms", "0"]),
+            Err(String::from("invalid refresh interval `0`"))
+        );
+        assert_eq!(
+            parse(&["--snapshot", "--size", "80"]),
+            Err(String::from("invalid size `80`, expected COLSxROWS"))
+        );
+        assert_eq!(
+            parse(&["--export", "csv"]),
+            Err(String::from(
+                "unknown export format `csv`, expected `json` or `ndjson`"
+            ))
+        );
+        assert_eq!(
+            parse(&["--listen", "localhost"]),
+            Err(String::from(
+                "invalid address `localhost`, expected IP:PORT"
+            ))
+        );
+        assert_eq!(parse(&["-x"]), Err(String::from("unknown option `-x`")));
+    }
+
+    #[test]
+    fn exclusive_options() {
+        for (args, error) in [
+            (
+                &["--snapshot", "--export", "json"][..],
+                "--snapshot and --export cannot be used together",
+            ),
+            (
+                &["--snapshot", "--listen", "[::1]:80"],
+                "--snapshot and --listen cannot be used together",
+            ),
+            (&["--headless"], "--headless can only be used with --listen"),
+            (
+                &["--listen", "[::1]:80", "--headless", "--export", "json"],
+                "--headless and --export cannot be used together",
+            ),
+            (
+                &["--export", "json", "-c", "a.json"],
+                "--export cannot be used with --config",
+            ),
+            (
+                &["--check-config", "--export", "ndjson"],
+                "--export cannot be used with --check-config",
+            ),
+            (
+                &["--check-config", "--listen", "[::1]:80"],
+                "--listen cannot be used with --check-config",
+            ),
+            (
+                &["--listen", "[::1]:80", "--headless", "-c", "a.json"],
+                "--headless cannot be used with --config",
+            ),
+            (
+                &["--record", "a", "--replay", "b"],
+                "--record and --replay cannot be used together",
+            ),
+            (
+                &["--replay", "a", "--snapshot"],
+                "--replay and --snapshot cannot be used together",
+            ),
+            (
+                &["--record", "a", "--export", "ndjson"],
+                "--record and --export cannot be used together",
+            ),
+        ] {
+            assert_eq!(parse(args), Err(String::from(error)), "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn options_of_a_mode() {
+        for (args, error) in [
+            (
+                &["--ticks", "3"][..],
+                "--ticks can only be used with --snapshot",
+            ),
+            (&["--size=80x24"], "--size can only be used with --snapshot"),
+            (&["--ansi"], "--ansi can only be used with --snapshot"),
+            (&["--count", "2"], "--count can only be used with --export"),
+            (
+                &["--output", "a"],
+                "--output can only be used with --export",
+            ),
+        ] {
+            assert_eq!(parse(args), Err(String::from(error)), "{:?}", args);
+        }
+        assert!(parse(&["--snapshot", "--ticks", "1", "--ansi"]).is_ok());
+        assert!(parse(&["--export", "json", "--count", "1", "--output", "a"]).is_ok());
+    }
+}
@@ -0,0 +1,869 @@
+use crate::{layout, theme};
+use serde::Deserialize;
+
+/// Names of the config file, looked up in this order in each config directory
+pub const CONFIG_FILE_NAMES: [&str; 5] = [
+    "config.json",
+    "config",
+    "config.toml",
+    "config.yaml",
+    "config.yml",
+];
+const SYSTEM_CONFIG_DIR: &str = "/etc/rtop";
+/// Directory of the theme files, in each config directory
+const THEMES_DIR: &str = "themes";
+/// Themes based on more themes than this are considered to be based on themselves
+const MAX_THEME_BASES: usize = 16;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl Format {
+    /// Format of the file at `path`, files without a known extension are JSON
+    pub fn from_path(path: &std::path::Path) -> Self {
+        match path.extension().and_then(std::ffi::OsStr::to_str) {
+            Some("toml") => Self::Toml,
+            Some("yaml" | "yml") => Self::Yaml,
+            _ => Self::Json,
+        }
+    }
+}
+
+/// Directories where the config is looked for: the user one, from `$XDG_CONFIG_HOME` or
+/// `~/.config`, then the system-wide one
+pub fn config_dirs() -> Vec<std::path::PathBuf> {
+    let user_config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(std::path::PathBuf::from)
+        // Relative paths are invalid in XDG variables and must be ignored
+        .filter(|path| path.is_absolute())
+        .or_else(|| home::home_dir().map(|home| home.join(".config")));
+
+    user_config_dir
+        .map(|dir| dir.join("rtop"))
+        .into_iter()
+        .chain([std::path::PathBuf::from(SYSTEM_CONFIG_DIR)])
+        .collect()
+}
+
+/// First config file found in `config_dirs`. When there is none, the place where the user
+/// should create it
+pub fn find_config() -> std::path::PathBuf {
+    let dirs = config_dirs();
+    dirs.iter()
+        .flat_map(|dir| CONFIG_FILE_NAMES.iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+        .unwrap_or_else(|| dirs[0].join(CONFIG_FILE_NAMES[0]))
+}
+
+fn default_pages() -> Vec<PageOption> {
+    vec![PageOption::Widgets(vec![
+        PageWidgetOption::Widget(String::from("cpu_chart")),
+        PageWidgetOption::Widget(String::from("memory_chart")),
+        PageWidgetOption::Widget(String::from("process_list")),
+    ])]
+}
+
+#[derive(Deserialize)]
+pub struct Config {
+    #[serde(default = "default_pages")]
+    pub pages: Vec<PageOption>,
+    #[serde(default)]
+    pub plugins: Vec<LibOption>,
+    /// Name of a built-in theme or a theme file, or a theme definition
+    #[serde(default)]
+    pub theme: Option<serde_json::Value>,
+}
+
+/// Description of the config types which can fail to match, for the parse errors of `serde`
+const CONFIG_TYPES: [(&str, &str); 1] = [(
+    "data did not match any variant of untagged enum TracksOption",
+    "grid rows and columns must be a number or a list of weights",
+)];
+
+impl Config {
+    /// Parse a config written in `format`, errors give the line and column of the problem
+    pub fn parse(content: &str, format: Format) -> Result<Self, String> {
+        parse_as(content, format).map_err(|mut message| {
+            for (config_type, description) in CONFIG_TYPES {
+                message = message.replace(config_type, description);
+            }
+            message
+        })
+    }
+
+    /// Theme of the config. Theme files are looked up in the `themes` directory next to the
+    /// config at `config_path`, then in the ones of `config_dirs`
+    pub fn theme(&self, config_path: &std::path::Path) -> Result<theme::Theme, String> {
+        let themes_dirs: Vec<std::path::PathBuf> = config_path
+            .parent()
+            .map(std::path::Path::to_path_buf)
+            .into_iter()
+            .chain(config_dirs())
+            .map(|dir| dir.join(THEMES_DIR))
+            .collect();
+
+        match &self.theme {
+            None => Ok(theme::Theme::default()),
+            Some(serde_json::Value::String(name)) => load_theme(name, &themes_dirs, 0),
+            Some(definition) => {
+                let definition = serde_json::from_value(definition.clone())
+                    .map_err(|error| format!("theme: {}", error))?;
+                resolve_theme(&definition, &themes_dirs, 0)
+            }
+        }
+    }
+
+    /// Problems which do not prevent parsing the config but make it unusable
+    pub fn check(&self) -> Vec<String> {
+        let mut problems = vec![];
+
+        let mut plugin_names = std::collections::HashSet::new();
+        for (i, plugin) in self.plugins.iter().enumerate() {
+            if plugin.name.is_empty() {
+                problems.push(format!("plugin {}: missing `name`", i + 1));
+            } else if plugin.name.contains('.') {
+                problems.push(format!(
+                    "plugin {}: name `{}` cannot contain a dot",
+                    i + 1,
+                    plugin.name
+                ));
+            } else if !plugin_names.insert(plugin.name.as_str()) {
+                problems.push(format!(
+                    "plugin {}: another plugin is already named `{}`",
+                    i + 1,
+                    plugin.name
+                ));
+            }
+            if plugin.path.is_empty() {
+                problems.push(format!("plugin {}: missing `path`", i + 1));
+            }
+        }
+
+        for (i, page) in self.pages.iter().enumerate() {
+            for problem in page.check() {
+                problems.push(format!("page {}: {}", i + 1, problem));
+            }
+        }
+        problems
+    }
+}
+
+fn parse_as<T: serde::de::DeserializeOwned>(content: &str, format: Format) -> Result<T, String> {
+    let parsed = match format {
+        Format::Json => serde_json::from_str(content).map_err(|error| error.to_string()),
+        Format::Toml => toml::from_str(content).map_err(|error| error.to_string()),
+        Format::Yaml => serde_yaml_ng::from_str(content).map_err(|error| error.to_string()),
+    };
+    parsed.map_err(|message| String::from(message.trim_end()))
+}
+
+/// Built-in theme `name`, or the one of the first `<name>.<extension>` file of `themes_dirs`
+fn load_theme(
+    name: &str,
+    themes_dirs: &[std::path::PathBuf],
+    bases: usize,
+) -> Result<theme::Theme, String> {
+    if let Some(builtin) = theme::Theme::builtin(name) {
+        return Ok(builtin);
+    }
+    if bases > MAX_THEME_BASES {
+        return Err(format!("theme `{}` is based on itself", name));
+    }
+
+    let path = themes_dirs
+        .iter()
+        .flat_map(|dir| {
+            ["json", "toml", "yaml", "yml"]
+                .iter()
+                .map(move |extension| dir.join(format!("{}.{}", name, extension)))
+        })
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            format!(
+                "unknown theme `{}`, expected one of {} or a theme file in {}",
+                name,
+                theme::BUILTIN_THEMES.join(", "),
+                themes_dirs
+                    .iter()
+                    .map(|dir| dir.display().to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )
+        })?;
+    let content = std::fs::read_to_string(&path)
+        .map_err(|error| format!("unable to read {}: {}", path.display(), error))?;
+    let definition = parse_as(&content, Format::from_path(&path))
+        .map_err(|error| format!("theme file {}: {}", path.display(), error))?;
+    resolve_theme(&definition, themes_dirs, bases + 1)
+}
+
+fn resolve_theme(
+    definition: &theme::ThemeDefinition,
+    themes_dirs: &[std::path::PathBuf],
+    bases: usize,
+) -> Result<theme::Theme, String> {
+    let base = match &definition.base {
+        Some(base) => load_theme(base, themes_dirs, bases)?,
+        None => theme::Theme::default(),
+    };
+    Ok(definition.apply_to(base))
+}
+
+#[derive(Deserialize)]
+pub struct LibOption {
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub name: String,
+}
+
+/// Deserialize `value` as the variant of a config type picked from the shape of the value,
+/// which keeps the errors of that variant, such as an unknown field, instead of failing to
+/// match any variant
+fn variant_from_value<'de, T, D>(value: serde_json::Value) -> Result<T, D::Error>
+where
+    T: serde::de::DeserializeOwned,
+    D: serde::Deserializer<'de>,
+{
+    serde_json::from_value(value).map_err(serde::de::Error::custom)
+}
+
+pub enum PageOption {
+    /// Widgets placed automatically depending on their count
+    Widgets(Vec<PageWidgetOption>),
+    Page(PageDefinition),
+    Layout(LayoutOption),
+}
+
+impl<'de> Deserialize<'de> for PageOption {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        match &value {
+            serde_json::Value::Array(_) => variant_from_value::<_, D>(value).map(Self::Widgets),
+            // A single widget is a layout, unless keys of a page object show a typo in `widgets`
+            serde_json::Value::Object(page)
+                if page.contains_key("split")
+                    || (page.contains_key("widget")
+                        && !["name", "layout", "widgets"]
+                            .iter()
+                            .any(|key| page.contains_key(*key))) =>
+            {
+                variant_from_value::<_, D>(value).map(Self::Layout)
+            }
+            serde_json::Value::Object(_) => variant_from_value::<_, D>(value).map(Self::Page),
+            serde_json::Value::String(_) => variant_from_value::<_, D>(value).map(Self::Layout),
+            _ => Err(serde::de::Error::custom(
+                "a page must be a list of widgets, a page object with `widgets` or a split",
+            )),
+        }
+    }
+}
+
+/// Rows or columns of a grid, either as a count of even tracks or as the weight of each track
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub enum TracksOption {
+    Count(usize),
+    Weights(Vec<u32>),
+}
+
+/// Most rows or columns of a grid, a count is only a number in the config and could otherwise
+/// allocate any amount of memory
+pub const MAX_GRID_TRACKS: usize = 64;
+
+impl TracksOption {
+    /// Weight of each track, counts being limited to `MAX_GRID_TRACKS`
+    pub fn weights(&self) -> Vec<u32> {
+        match self {
+            Self::Count(count) => vec![1; (*count).min(MAX_GRID_TRACKS)],
+            Self::Weights(weights) => weights.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GridOption {
+    pub rows: TracksOption,
+    pub columns: TracksOption,
+}
+
+/// Layout of a page as written in the config file
+pub enum LayoutOption {
+    /// Name of a widget, taking one share of the space
+    Widget(String),
+    SizedWidget(SizedWidgetOption),
+    Split(SplitOption),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SizedWidgetOption {
+    widget: String,
+    #[serde(default)]
+    ratio: Option<u32>,
+    #[serde(default)]
+    size: Option<i32>,
+    #[serde(default)]
+    options: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SplitOption {
+    split: layout::Direction,
+    children: Vec<LayoutOption>,
+    #[serde(default)]
+    ratio: Option<u32>,
+    #[serde(default)]
+    size: Option<i32>,
+}
+
+impl<'de> Deserialize<'de> for LayoutOption {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        match &value {
+            serde_json::Value::String(widget) => Ok(Self::Widget(widget.clone())),
+            serde_json::Value::Object(child) if child.contains_key("split") => {
+                variant_from_value::<_, D>(value).map(Self::Split)
+            }
+            serde_json::Value::Object(_) => {
+                variant_from_value::<_, D>(value).map(Self::SizedWidget)
+            }
+            _ => Err(serde::de::Error::custom(
+                "a split child must be a widget name, an object with `widget` or a split",
+            )),
+        }
+    }
+}
+
+impl LayoutOption {
+    /// Widgets, in the order used by `Layout::compute`
+    pub fn widgets(&self) -> Vec<WidgetEntry> {
+        match self {
+            Self::Widget(widget) => vec![WidgetEntry::new(widget, &serde_json::Value::Null)],
+            Self::SizedWidget(sized) => vec![WidgetEntry::new(&sized.widget, &sized.options)],
+            Self::Split(split) => split.children.iter().flat_map(Self::widgets).collect(),
+        }
+    }
+
+    fn check(&self, problems: &mut Vec<String>) {
+        let size = match self {
+            Self::Widget(_) => None,
+            Self::SizedWidget(SizedWidgetOption { size, .. })
+            | Self::Split(SplitOption { size, .. }) => *size,
+        };
+        if size.is_some_and(|size| size < 0) {
+            problems.push(String::from("a size cannot be negative"));
+        }
+
+        if let Self::Split(split) = self {
+            if split.children.is_empty() {
+                problems.push(String::from("a split needs at least one child"));
+            }
+            for child in &split.children {
+                child.check(problems);
+            }
+        }
+    }
+
+    pub fn to_layout(&self) -> layout::Layout {
+        let size = |ratio: &Option<u32>, size: &Option<i32>| match (ratio, size) {
+            (_, Some(size)) => layout::Size::Fixed(*size),
+            (Some(ratio), None) => layout::Size::Ratio(*ratio),
+            (None, None) => layout::Size::Ratio(1),
+        };
+
+        match self {
+            Self::Widget(_) => layout::Layout::Widget(layout::Size::Ratio(1)),
+            Self::SizedWidget(sized) => layout::Layout::Widget(size(&sized.ratio, &sized.size)),
+            Self::Split(split) => layout::Layout::Split {
+                direction: split.split,
+                size: size(&split.ratio, &split.size),
+                children: split.children.iter().map(Self::to_layout).collect(),
+            },
+        }
+    }
+}
+
+/// Page with a name and, optionally, a grid placing its widgets
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PageDefinition {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub layout: Option<GridOption>,
+    pub widgets: Vec<PageWidgetOption>,
+}
+
+pub enum PageWidgetOption {
+    Widget(String),
+    Placed(PlacedWidgetOption),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlacedWidgetOption {
+    widget: String,
+    #[serde(default)]
+    row: Option<usize>,
+    #[serde(default)]
+    column: Option<usize>,
+    #[serde(default = "default_span")]
+    row_span: usize,
+    #[serde(default = "default_span")]
+    column_span: usize,
+    #[serde(default)]
+    options: serde_json::Value,
+}
+
+impl<'de> Deserialize<'de> for PageWidgetOption {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        match &value {
+            serde_json::Value::String(widget) => Ok(Self::Widget(widget.clone())),
+            serde_json::Value::Object(_) => variant_from_value::<_, D>(value).map(Self::Placed),
+            _ => Err(serde::de::Error::custom(
+                "a widget must be a name or an object with `widget`",
+            )),
+        }
+    }
+}
+
+/// Widget of a page, with the options handed to its initializer
+#[derive(Clone, PartialEq, Debug)]
+pub struct WidgetEntry {
+    pub name: String,
+    /// Always a JSON object, empty when the config gives no options
+    pub options: serde_json::Value,
+}
+
+impl WidgetEntry {
+    fn new(name: &str, options: &serde_json::Value) -> Self {
+        Self {
+            name: String::from(name),
+            options: if options.is_null() {
+                serde_json::Value::Object(serde_json::Map::new())
+            } else {
+                options.clone()
+            },
+        }
+    }
+}
+
+fn default_span() -> usize {
+    1
+}
+
+impl PageWidgetOption {
+    pub fn entry(&self) -> WidgetEntry {
+        match self {
+            Self::Widget(widget) => WidgetEntry::new(widget, &serde_json::Value::Null),
+            Self::Placed(placed) => WidgetEntry::new(&placed.widget, &placed.options),
+        }
+    }
+
+    fn position(&self) -> (Option<usize>, Option<usize>, usize, usize) {
+        match self {
+            Self::Widget(_) => (None, None, 1, 1),
+            Self::Placed(placed) => (
+                placed.row,
+                placed.column,
+                placed.row_span,
+                placed.column_span,
+            ),
+        }
+    }
+}
+
+impl PageOption {
+    pub fn name(&self) -> Option<String> {
+        match self {
+            Self::Page(page) => page.name.clone(),
+            Self::Widgets(_) | Self::Layout(_) => None,
+        }
+    }
+
+    /// Widgets, in the order of the areas computed by the page layout
+    pub fn widgets(&self) -> Vec<WidgetEntry> {
+        match self {
+            Self::Widgets(widgets) => widgets.iter().map(PageWidgetOption::entry).collect(),
+            Self::Page(page) => page.widgets.iter().map(PageWidgetOption::entry).collect(),
+            Self::Layout(layout) => layout.widgets(),
+        }
+    }
+
+    fn check(&self) -> Vec<String> {
+        let mut problems = vec![];
+        let widgets = self.widgets();
+        if widgets.is_empty() {
+            problems.push(String::from("no widget in this page"));
+        }
+        for widget in &widgets {
+            if !widget.options.is_object() {
+                problems.push(format!(
+                    "the options of widget `{}` must be an object",
+                    widget.name
+                ));
+            }
+        }
+
+        match self {
+            Self::Widgets(_) => {}
+            Self::Page(page) => {
+                if let Some(grid) = &page.layout {
+                    let rows = grid.rows.weights().len();
+                    let columns = grid.columns.weights().len();
+                    if rows == 0 || columns == 0 {
+                        problems.push(String::from("the grid needs at least one row and column"));
+                    }
+                    for (name, tracks) in [("rows", &grid.rows), ("columns", &grid.columns)] {
+                        let count = match tracks {
+                            TracksOption::Count(count) => *count,
+                            TracksOption::Weights(weights) => weights.len(),
+                        };
+                        if count > MAX_GRID_TRACKS {
+                            problems.push(format!(
+                                "the grid can have at most {} {}, found {}",
+                                MAX_GRID_TRACKS, name, count
+                            ));
+                        }
+                        if let TracksOption::Weights(weights) = tracks {
+                            if weights.contains(&0) {
+                                problems.push(format!(
+                                    "the weights of the grid {} must be greater than 0",
+                                    name
+                                ));
+                            }
+                        }
+                    }
+
+                    let areas = self.grid_areas();
+                    for (i, (widget, area)) in page.widgets.iter().zip(&areas).enumerate() {
+                        let (row, column, ..) = widget.position();
+                        if row.is_some_and(|row| row >= rows)
+                            || column.is_some_and(|column| column >= columns)
+                        {
+                            problems.push(format!(
+                                "widget `{}` is outside of the {}x{} grid",
+                                widget.entry().name,
+                                rows,
+                                columns
+                            ));
+                        } else if area.row >= rows {
+                            problems.push(format!(
+                                "no free cell left in the grid for widget `{}`",
+                                widget.entry().name
+                            ));
+                        } else if let Some(other) = page
+                            .widgets
+                            .iter()
+                            .zip(&areas)
+                            .take(i)
+                            .find(|(_, other_area)| {
+                                other_area.row < rows
+                                    && other_area.column < columns
+                                    && other_area.overlaps(area)
+                            })
+                            .map(|(other, _)| other)
+                        {
+                            problems.push(format!(
+                                "widgets `{}` and `{}` overlap in the grid",
+                                other.entry().name,
+                                widget.entry().name
+                            ));
+                        }
+                    }
+                }
+            }
+            Self::Layout(layout) => layout.check(&mut problems),
+        }
+        problems
+    }
+
+    fn grid_areas(&self) -> Vec<layout::GridArea> {
+        match self {
+            Self::Page(PageDefinition {
+                layout: Some(grid),
+                widgets,
+                ..
+            }) => {
+                let positions: Vec<(Option<usize>, Option<usize>, usize, usize)> =
+                    widgets.iter().map(PageWidgetOption::position).collect();
+                layout::place_in_grid(
+                    grid.rows.weights().len(),
+                    grid.columns.weights().len(),
+                    &positions,
+                )
+            }
+            Self::Widgets(_) | Self::Page(_) | Self::Layout(_) => vec![],
+        }
+    }
+
+    pub fn layout(&self) -> layout::Layout {
+        match self {
+            Self::Widgets(widgets) => layout::Layout::auto(widgets.len()),
+            Self::Page(page) => {
+                if let Some(grid) = &page.layout {
+                    layout::Layout::Grid {
+                        areas: self.grid_areas(),
+                        rows: grid.rows.weights(),
+                        columns: grid.columns.weights(),
+                    }
+                } else {
+                    layout::Layout::auto(page.widgets.len())
+                }
+            }
+            Self::Layout(layout) => layout.to_layout(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_track_counts_are_limited() {
+        assert_eq!(TracksOption::Count(3).weights(), vec![1, 1, 1]);
+        assert_eq!(
+            TracksOption::Count(1_000_000_000).weights().len(),
+            MAX_GRID_TRACKS
+        );
+        assert_eq!(TracksOption::Weights(vec![2, 1]).weights(), vec![2, 1]);
+    }
+
+    fn problems(content: &str) -> Vec<String> {
+        Config::parse(content, Format::Json).unwrap().check()
+    }
+
+    #[test]
+    fn formats_by_extension() {
+        assert_eq!(
+            Format::from_path(std::path::Path::new("config.toml")),
+            Format::Toml
+        );
+        assert_eq!(
+            Format::from_path(std::path::Path::new("a.yml")),
+            Format::Yaml
+        );
+        assert_eq!(
+            Format::from_path(std::path::Path::new("config")),
+            Format::Json
+        );
+
+        let pages = |content: &str, format: Format| {
+            Config::parse(content, format)
+                .unwrap()
+                .pages
+                .iter()
+                .map(PageOption::widgets)
+                .collect::<Vec<Vec<WidgetEntry>>>()
+        };
+        let json = pages(
+            r#"{"pages": [["cpu_chart", {"widget": "process_list", "options": {"mode": "tree"}}]]}"#,
+            Format::Json,
+        );
+        let toml = pages(
+            "pages = [[\"cpu_chart\", { widget = \"process_list\", options = { mode = \"tree\" } }]]",
+            Format::Toml,
+        );
+        let yaml = pages(
+            "pages:\n  - - cpu_chart\n    - widget: process_list\n      options:\n        mode: tree\n",
+            Format::Yaml,
+        );
+        assert_eq!(json[0].len(), 2);
+        assert_eq!(json, toml);
+        assert_eq!(json, yaml);
+    }
+
+    #[test]
+    fn parse_errors_describe_the_config_types() {
+        assert_eq!(
+            Config::parse(r#"{"pages": [3]}"#, Format::Json).err(),
+            Some(String::from(
+                "a page must be a list of widgets, a page object with `widgets` or a split at line 1 column 13"
+            ))
+        );
+
+        // Typos in the keys are reported, in the variant matching the shape of the value
+        let error = |content: &str| Config::parse(content, Format::Json).err();
+        for (content, message) in [
+            (
+                r#"{"pages": [[{"widget": "cpu_chart", "colspan": 2}]]}"#,
+                "unknown field `colspan`, expected one of `widget`, `row`, `column`, `row_span`, `column_span`, `options` at line 1 column 51",
+            ),
+            (
+                r#"{"pages": [{"widgets": ["cpu_chart"], "layout": {"rows": 1, "columns": 1, "rowspan": 1}}]}"#,
+                "unknown field `rowspan`, expected `rows` or `columns` at line 1 column 89",
+            ),
+            (
+                r#"{"pages": [{"name": "cpu", "widget": ["cpu_chart"]}]}"#,
+                "unknown field `widget`, expected one of `name`, `layout`, `widgets` at line 1 column 52",
+            ),
+            (
+                r#"{"pages": [{"split": "vertical", "children": [{"widget": "cpu_chart", "sizee": 2}]}]}"#,
+                "unknown field `sizee`, expected one of `widget`, `ratio`, `size`, `options` at line 1 column 84",
+            ),
+            (
+                r#"{"pages": [{"split": "vertical", "children": [3]}]}"#,
+                "a split child must be a widget name, an object with `widget` or a split at line 1 column 50",
+            ),
+            (
+                r#"{"pages": [{"widgets": ["cpu_chart"], "names": "cpu"}]}"#,
+                "unknown field `names`, expected one of `name`, `layout`, `widgets` at line 1 column 54",
+            ),
+            (
+                r#"{"pages": [[true]]}"#,
+                "a widget must be a name or an object with `widget` at line 1 column 18",
+            ),
+        ] {
+            assert_eq!(error(content).as_deref(), Some(message), "{}", content);
+        }
+    }
+
+    #[test]
+    fn plugin_and_page_problems() {
+        assert_eq!(
+            problems(
+                r#"{"plugins": [
+                    {"path": "a.so", "name": "a"},
+                    {"name": "a.b"},
+                    {"path": "c.so", "name": "a"},
+                    {"path": "d.so"}
+                ], "pages": [
+                    [],
+                    {"split": "horizontal", "size": -1, "children": [
+                        {"split": "vertical", "children": []}
+                    ]}
+                ]}"#
+            ),
+            vec![
+                "plugin 2: name `a.b` cannot contain a dot",
+                "plugin 2: missing `path`",
+                "plugin 3: another plugin is already named `a`",
+                "plugin 4: missing `name`",
+                "page 1: no widget in this page",
+                "page 2: no widget in this page",
+                "page 2: a size cannot be negative",
+                "page 2: a split needs at least one child",
+            ]
+        );
+        assert!(problems("{}").is_empty());
+    }
+
+    #[test]
+    fn themes_from_files() {
+        let config_dir = crate::test_files::TempDir::new("themes");
+        let dir = &config_dir.path;
+        std::fs::create_dir_all(dir.join(THEMES_DIR)).unwrap();
+        std::fs::write(
+            dir.join(THEMES_DIR).join("dim.toml"),
+            "base = \"nord\"\nborder_style = \"double\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(THEMES_DIR).join("loop.json"),
+            r#"{"base": "loop"}"#,
+        )
+        .unwrap();
+        let theme = |content: &str| {
+            Config::parse(content, Format::Json)
+                .unwrap()
+                .theme(&dir.join("config.json"))
+        };
+
+        let dim = theme(r#"{"theme": {"base": "dim", "text": "red"}}"#);
+        let looping = theme(r#"{"theme": "loop"}"#);
+        let unknown = theme(r#"{"theme": "missing"}"#);
+
+        assert_eq!(
+            dim,
+            Ok(theme::Theme {
+                text: theme::Style {
+                    color: theme::Color::Basic(1),
+                    bold: false,
+                },
+                border_style: theme::BorderStyle::Double,
+                ..theme::Theme::builtin("nord").unwrap()
+            })
+        );
+        assert_eq!(
+            looping,
+            Err(String::from("theme `loop` is based on itself"))
+        );
+        assert!(unknown.is_err_and(|error| error.starts_with("unknown theme `missing`")));
+    }
+
+    #[test]
+    fn options_must_be_an_object() {
+        assert_eq!(
+            problems(
+                r#"{"pages": [
+                    [{"widget": "cpu_chart", "options": "red"}, {"widget": "memory_chart"}],
+                    {"split": "vertical", "children": [
+                        {"widget": "process_list", "options": {"mode": "tree"}},
+                        {"widget": "disk_io", "options": [1]}
+                    ]}
+                ]}"#
+            ),
+            vec![
+                "page 1: the options of widget `cpu_chart` must be an object",
+                "page 2: the options of widget `disk_io` must be an object",
+            ]
+        );
+    }
+
+    #[test]
+    fn grid_placement_problems() {
+        assert_eq!(
+            problems(
+                r#"{"pages": [{"layout": {"rows": 2, "columns": 2}, "widgets": [
+                    {"widget": "cpu_chart", "row": 0, "column": 0, "column_span": 2},
+                    {"widget": "memory_chart", "row": 0, "column": 1},
+                    {"widget": "process_list", "row": 2, "column": 0},
+                    "network_chart",
+                    "disk_chart",
+                    "sensors"
+                ]}]}"#
+            ),
+            vec![
+                "page 1: widgets `cpu_chart` and `memory_chart` overlap in the grid",
+                "page 1: widget `process_list` is outside of the 2x2 grid",
+                "page 1: no free cell left in the grid for widget `sensors`",
+            ]
+        );
+        assert_eq!(
+            problems(
+                r#"{"pages": [{"layout": {"rows": [1, 2], "columns": 2}, "widgets": [
+                    {"widget": "cpu_chart", "row_span": 2},
+                    {"widget": "memory_chart", "row": 1, "column": 0}
+                ]}]}"#
+            ),
+            vec!["page 1: widgets `cpu_chart` and `memory_chart` overlap in the grid"]
+        );
+    }
+
+    #[test]
+    fn grid_track_problems() {
+        assert_eq!(
+            problems(
+                r#"{"pages": [
+                    {"layout": {"rows": 1000, "columns": [1, 0]}, "widgets": ["cpu_chart"]},
+                    {"layout": {"rows": 0, "columns": 1}, "widgets": ["cpu_chart"]}
+                ]}"#
+            ),
+            vec![
+                "page 1: the grid can have at most 64 rows, found 1000",
+                "page 1: the weights of the grid columns must be greater than 0",
+                "page 2: the grid needs at least one row and column",
+                "page 2: no free cell left in the grid for widget `cpu_chart`",
+            ]
+        );
+    }
+}
@@ -0,0 +1,390 @@
+use crate::metrics;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+const TEXT_CONTENT_TYPE: &str = "text/plain; charset=utf-8";
+/// Requests with a longer head are rejected
+const MAX_REQUEST_SIZE: usize = 8192;
+/// Clients which do not send their request in this time are answered with an error
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Serve the latest sample at `/metrics` to the clients of `listener`
+pub async fn serve(listener: tokio::net::TcpListener, samples: metrics::Samples) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let samples = samples.clone();
+                // Errors only concern the client which caused them
+                tokio::spawn(async move {
+                    handle_client(stream, &samples, REQUEST_TIMEOUT)
+                        .await
+                        .unwrap_or_default();
+                });
+            }
+            // Such as running out of file descriptors, which may be temporary
+            Err(_) => tokio::time::sleep(std::time::Duration::from_millis(100)).await,
+        }
+    }
+}
+
+/// Head of the request of `stream`, cut after `MAX_REQUEST_SIZE` bytes. `None` if the client
+/// left without sending it
+async fn read_request(stream: &mut tokio::net::TcpStream) -> std::io::Result<Option<Vec<u8>>> {
+    let mut request = vec![];
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        if request.len() > MAX_REQUEST_SIZE {
+            break;
+        }
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+    Ok(Some(request))
+}
+
+async fn handle_client(
+    mut stream: tokio::net::TcpStream,
+    samples: &metrics::Samples,
+    timeout: std::time::Duration,
+) -> std::io::Result<()> {
+    let request = match tokio::time::timeout(timeout, read_request(&mut stream)).await {
+        Ok(Ok(Some(request))) => request,
+        Ok(Ok(None)) => return Ok(()),
+        Ok(Err(error)) => return Err(error),
+        Err(_) => {
+            return write_response(
+                &mut stream,
+                "408 Request Timeout",
+                TEXT_CONTENT_TYPE,
+                "",
+                true,
+            )
+            .await
+        }
+    };
+    // The head is incomplete only when it is too large
+    if !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        return write_response(
+            &mut stream,
+            "431 Request Header Fields Too Large",
+            TEXT_CONTENT_TYPE,
+            "",
+            true,
+        )
+        .await;
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+    let path = path.split_once('?').map_or(path, |(path, _)| path);
+
+    let (status, content_type, body) = if method != "GET" && method != "HEAD" {
+        ("405 Method Not Allowed", TEXT_CONTENT_TYPE, String::new())
+    } else if path == "/metrics" {
+        match &*samples.borrow() {
+            Some(sample) => ("200 OK", CONTENT_TYPE, render(sample)),
+            None => (
+                "503 Service Unavailable",
+                TEXT_CONTENT_TYPE,
+                String::from("No sample taken yet\n"),
+            ),
+        }
+    } else if path == "/" {
+        (
+            "200 OK",
+            TEXT_CONTENT_TYPE,
+            String::from("rtop exporter, the metrics are at /metrics\n"),
+        )
+    } else {
+        ("404 Not Found", TEXT_CONTENT_TYPE, String::new())
+    };
+
+    write_response(&mut stream, status, content_type, &body, method != "HEAD").await
+}
+
+async fn write_response(
+    stream: &mut tokio::net::TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+    with_body: bool,
+) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        if with_body { body } else { "" }
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// `sample` in the OpenMetrics text format
+pub fn render(sample: &metrics::Sample) -> String {
+    let mut output = String::new();
+
+    write_family(
+        &mut output,
+        "rtop_cpu_usage_ratio",
+        None,
+        "Usage of all the CPUs",
+        &[(String::new(), ratio(sample.cpu_percent))],
+    );
+    write_family(
+        &mut output,
+        "rtop_cpu_core_usage_ratio",
+        None,
+        "Usage of each CPU",
+        &sample
+            .cpu_cores_percent
+            .iter()
+            .enumerate()
+            .map(|(core, usage)| (format!("core=\"{}\"", core), ratio(*usage)))
+            .collect::<Vec<(String, f64)>>(),
+    );
+
+    let memory = &sample.memory;
+    for (name, help, value) in [
+        ("rtop_memory_total_bytes", "Total memory", memory.total),
+        ("rtop_memory_used_bytes", "Used memory", memory.used),
+        (
+            "rtop_memory_available_bytes",
+            "Memory available to start applications without swapping",
+            memory.available,
+        ),
+        ("rtop_swap_total_bytes", "Total swap", memory.swap_total),
+        ("rtop_swap_used_bytes", "Used swap", memory.swap_used),
+    ] {
+        write_family(
+            &mut output,
+            name,
+            Some("bytes"),
+            help,
+            &[(String::new(), value as f64)],
+        );
+    }
+
+    for (name, help, value) in [
+        (
+            "rtop_load1",
+            "Load average over 1 minute",
+            sample.load_average.one,
+        ),
+        (
+            "rtop_load5",
+            "Load average over 5 minutes",
+            sample.load_average.five,
+        ),
+        (
+            "rtop_load15",
+            "Load average over 15 minutes",
+            sample.load_average.fifteen,
+        ),
+    ] {
+        write_family(&mut output, name, None, help, &[(String::new(), value)]);
+    }
+
+    let group_samples = |value: &dyn Fn(&metrics::ProcessGroup) -> f64| {
+        sample
+            .processes
+            .iter()
+            .map(|group| {
+                (
+                    format!("name=\"{}\"", escape_label(&group.name)),
+                    value(group),
+                )
+            })
+            .collect::<Vec<(String, f64)>>()
+    };
+    write_family(
+        &mut output,
+        "rtop_process_group_cpu_usage_ratio",
+        None,
+        "CPU usage of the processes sharing a name, as a share of all the physical cores",
+        &group_samples(&|group| ratio(group.cpu_percent)),
+    );
+    write_family(
+        &mut output,
+        "rtop_process_group_memory_bytes",
+        Some("bytes"),
+        "Resident memory of the processes sharing a name",
+        &group_samples(&|group| group.memory as f64),
+    );
+    write_family(
+        &mut output,
+        "rtop_process_group_processes",
+        None,
+        "Number of processes sharing a name",
+        &group_samples(&|group| group.count as f64),
+    );
+
+    output += "# EOF\n";
+    output
+}
+
+/// Write a gauge, `samples` being the labels and the value of each sample
+fn write_family(
+    output: &mut String,
+    name: &str,
+    unit: Option<&str>,
+    help: &str,
+    samples: &[(String, f64)],
+) {
+    *output += &format!("# TYPE {} gauge\n", name);
+    if let Some(unit) = unit {
+        *output += &format!("# UNIT {} {}\n", name, unit);
+    }
+    *output += &format!("# HELP {} {}\n", name, help);
+    for (labels, value) in samples {
+        if labels.is_empty() {
+            *output += &format!("{} {}\n", name, value);
+        } else {
+            *output += &format!("{}{{{}}} {}\n", name, labels, value);
+        }
+    }
+}
+
+/// `percent` as a ratio, without the noise of converting an `f32` to an `f64`
+fn ratio(percent: f32) -> f64 {
+    (f64::from(percent) * 1e4).round() / 1e6
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> metrics::Sample {
+        metrics::Sample {
+            timestamp: String::from("2024-01-01T00:00:00+00:00"),
+            cpu_percent: 12.5,
+            cpu_cores_percent: vec![25., 0.],
+            memory: metrics::MemorySample {
+                total: 1000,
+                used: 250,
+                available: 700,
+                percent: 25.,
+                swap_total: 0,
+                swap_used: 0,
+                swap_percent: 0.,
+            },
+            load_average: metrics::LoadAverage {
+                one: 0.5,
+                five: 0.25,
+                fifteen: 0.,
+            },
+            processes: vec![metrics::ProcessGroup {
+                name: String::from("a \"b\"\\c"),
+                count: 2,
+                cpu_percent: 50.,
+                memory: 100,
+                memory_percent: 10.,
+                pids: vec![],
+            }],
+        }
+    }
+
+    async fn get(address: std::net::SocketAddr, request: &str) -> String {
+        let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    async fn start_server(sample: Option<metrics::Sample>) -> std::net::SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, tokio::sync::watch::channel(sample).1));
+        address
+    }
+
+    #[test]
+    fn render_sample() {
+        let rendered = render(&sample());
+        for line in [
+            "# TYPE rtop_cpu_usage_ratio gauge",
+            "rtop_cpu_usage_ratio 0.125",
+            "rtop_cpu_core_usage_ratio{core=\"0\"} 0.25",
+            "rtop_cpu_core_usage_ratio{core=\"1\"} 0",
+            "# UNIT rtop_memory_used_bytes bytes",
+            "rtop_memory_used_bytes 250",
+            "rtop_load5 0.25",
+            "rtop_process_group_cpu_usage_ratio{name=\"a \\\"b\\\"\\\\c\"} 0.5",
+            "rtop_process_group_memory_bytes{name=\"a \\\"b\\\"\\\\c\"} 100",
+            "rtop_process_group_processes{name=\"a \\\"b\\\"\\\\c\"} 2",
+        ] {
+            assert!(rendered.lines().any(|l| l == line), "missing {}", line);
+        }
+        assert!(rendered.ends_with("\n# EOF\n"));
+    }
+
+    #[tokio::test]
+    async fn serve_metrics() {
+        let address = start_server(Some(sample())).await;
+
+        let response = get(address, "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").await;
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(head.contains(&format!("Content-Type: {}\r\n", CONTENT_TYPE)));
+        assert!(head.contains(&format!("Content-Length: {}", body.len())));
+        assert_eq!(body, render(&sample()));
+
+        let response = get(address, "HEAD /metrics?x=1 HTTP/1.1\r\n\r\n").await;
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(head.contains(&format!("Content-Length: {}", render(&sample()).len())));
+        assert_eq!(body, "");
+    }
+
+    #[tokio::test]
+    async fn serve_errors() {
+        let address = start_server(None).await;
+        let status = |response: String| String::from(response.lines().next().unwrap());
+
+        assert_eq!(
+            status(get(address, "GET /metrics HTTP/1.1\r\n\r\n").await),
+            "HTTP/1.1 503 Service Unavailable"
+        );
+        assert_eq!(
+            status(get(address, "GET /other HTTP/1.1\r\n\r\n").await),
+            "HTTP/1.1 404 Not Found"
+        );
+        assert_eq!(
+            status(get(address, "POST /metrics HTTP/1.1\r\n\r\n").await),
+            "HTTP/1.1 405 Method Not Allowed"
+        );
+        assert_eq!(
+            status(get(address, &"X".repeat(MAX_REQUEST_SIZE + 1)).await),
+            "HTTP/1.1 431 Request Header Fields Too Large"
+        );
+    }
+
+    #[tokio::test]
+    async fn answer_idle_clients() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let samples = tokio::sync::watch::channel(Some(sample())).1;
+            handle_client(stream, &samples, std::time::Duration::from_millis(50))
+                .await
+                .unwrap();
+        });
+
+        let response = get(address, "GET /metrics HTTP/1.1\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 408 Request Timeout\r\n"));
+    }
+}
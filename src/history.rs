@@ -0,0 +1,269 @@
+use std::collections::VecDeque;
+
+/// Points kept at full resolution, enough for a chart as wide as a large terminal
+const RECENT_POINTS: usize = 1024;
+/// Points summarized by a bucket once they leave the full resolution
+const BUCKET_POINTS: usize = 16;
+/// Points kept by default, an hour of updates every 333 ms
+pub const DEFAULT_RETENTION: usize = 10_800;
+
+/// Minimum, average and maximum of consecutive points
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bucket {
+    pub min: i32,
+    pub max: i32,
+    sum: f64,
+    /// Number of points summarized
+    count: usize,
+}
+
+impl Bucket {
+    fn new(value: i32) -> Self {
+        Self {
+            min: value,
+            max: value,
+            sum: f64::from(value),
+            count: 1,
+        }
+    }
+
+    pub fn average(&self) -> i32 {
+        (self.sum / self.count.max(1) as f64).round() as i32
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.sum += other.sum;
+        self.count += other.count;
+    }
+
+    /// Take `count` points out of the bucket at most. The points of a bucket are not known
+    /// anymore, so both parts keep its minimum and maximum
+    fn split_off(&mut self, count: usize) -> Self {
+        let count = count.min(self.count);
+        let sum = self.sum * count as f64 / self.count as f64;
+        self.sum -= sum;
+        self.count -= count;
+        Self {
+            sum,
+            count,
+            ..*self
+        }
+    }
+}
+
+/// History of a chart with a bounded size: the latest points are kept as they are, older ones
+/// are summarized by buckets until the retention is reached
+pub struct History {
+    /// The oldest first
+    recent: VecDeque<i32>,
+    /// The oldest first
+    buckets: VecDeque<Bucket>,
+    /// Points which left `recent`, until they fill a bucket
+    pending: Option<Bucket>,
+    recent_capacity: usize,
+    bucket_capacity: usize,
+}
+
+impl History {
+    /// History of the last `retention` points, give or take a bucket
+    pub fn new(retention: usize) -> Self {
+        let retention = retention.max(1);
+        let recent_capacity = retention.min(RECENT_POINTS);
+        Self {
+            recent: VecDeque::with_capacity(recent_capacity),
+            buckets: VecDeque::new(),
+            pending: None,
+            recent_capacity,
+            bucket_capacity: (retention - recent_capacity).div_ceil(BUCKET_POINTS),
+        }
+    }
+
+    pub fn push(&mut self, value: i32) {
+        if self.recent.len() == self.recent_capacity {
+            if let Some(oldest) = self.recent.pop_front() {
+                self.downsample(oldest);
+            }
+        }
+        self.recent.push_back(value);
+    }
+
+    fn downsample(&mut self, value: i32) {
+        if self.bucket_capacity == 0 {
+            return;
+        }
+        let pending = match &mut self.pending {
+            Some(pending) => {
+                pending.merge(&Bucket::new(value));
+                pending
+            }
+            None => self.pending.insert(Bucket::new(value)),
+        };
+        if pending.count == BUCKET_POINTS {
+            if self.buckets.len() == self.bucket_capacity {
+                self.buckets.pop_front();
+            }
+            self.buckets.extend(self.pending.take());
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.recent.clear();
+        self.buckets.clear();
+        self.pending = None;
+    }
+
+    /// Latest point
+    pub fn last(&self) -> Option<i32> {
+        self.recent.back().copied()
+    }
+
+    /// Number of points kept, including the summarized ones
+    pub fn len(&self) -> usize {
+        self.recent.len()
+            + self.pending.map_or(0, |pending| pending.count)
+            + self.buckets.len() * BUCKET_POINTS
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recent.is_empty()
+    }
+
+    /// The latest `count` groups of `zoom` consecutive points at most, the oldest first. The
+    /// oldest group may have fewer points
+    pub fn window(&self, count: usize, zoom: usize) -> Vec<Bucket> {
+        let zoom = zoom.max(1);
+        let segments = self
+            .recent
+            .iter()
+            .rev()
+            .map(|value| Bucket::new(*value))
+            .chain(self.pending)
+            .chain(self.buckets.iter().rev().copied());
+
+        let mut window = vec![];
+        let mut group: Option<Bucket> = None;
+        for mut segment in segments {
+            while segment.count > 0 && window.len() < count {
+                let missing = zoom - group.map_or(0, |group| group.count);
+                let part = segment.split_off(missing);
+                let group_count = match &mut group {
+                    Some(group) => {
+                        group.merge(&part);
+                        group.count
+                    }
+                    None => group.insert(part).count,
+                };
+                if group_count == zoom {
+                    window.extend(group.take());
+                }
+            }
+        }
+        if window.len() < count {
+            window.extend(group);
+        }
+        window.reverse();
+        window
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// History of `retention` points after pushing `count` points, numbered from 0
+    fn history_of(retention: usize, count: i32) -> History {
+        let mut history = History::new(retention);
+        for value in 0..count {
+            history.push(value);
+        }
+        history
+    }
+
+    /// Minimum, average and maximum of each bucket of `window`
+    fn summary(window: &[Bucket]) -> Vec<(i32, i32, i32)> {
+        window
+            .iter()
+            .map(|bucket| (bucket.min, bucket.average(), bucket.max))
+            .collect()
+    }
+
+    #[test]
+    fn retention_below_the_recent_points() {
+        let history = history_of(100, 300);
+        assert_eq!(history.len(), 100);
+        assert_eq!(history.last(), Some(299));
+        let window = history.window(1000, 1);
+        assert_eq!(window.len(), 100);
+        assert_eq!(summary(&window[..1]), [(200, 200, 200)]);
+    }
+
+    #[test]
+    fn retention_of_the_recent_points() {
+        let history = history_of(RECENT_POINTS, 2000);
+        assert_eq!(history.len(), RECENT_POINTS);
+        assert!(history.buckets.is_empty() && history.pending.is_none());
+        assert_eq!(summary(&history.window(1, 1024)), [(976, 1488, 1999)]);
+    }
+
+    #[test]
+    fn retention_above_the_recent_points() {
+        // Two buckets, filled then replaced by newer ones
+        let retention = RECENT_POINTS + 2 * BUCKET_POINTS;
+        let history = history_of(retention, retention as i32);
+        assert_eq!(history.len(), retention);
+        assert_eq!(history.buckets.len(), 2);
+
+        let history = history_of(retention, retention as i32 + 3 * 16 + 8);
+        assert_eq!(history.buckets.len(), 2);
+        assert_eq!(history.pending.map(|pending| pending.count), Some(8));
+        assert_eq!(history.len(), retention + 8);
+        assert_eq!(
+            summary(&history.window(1000, 16)[..1]),
+            [(48, 56, 63)],
+            "the oldest buckets are dropped"
+        );
+    }
+
+    #[test]
+    fn window_with_zoom_dividing_the_buckets() {
+        let history = history_of(RECENT_POINTS + 32, RECENT_POINTS as i32 + 32);
+        let window = history.window(1000, 4);
+        assert_eq!(window.len(), (RECENT_POINTS + 32) / 4);
+        // The points of a bucket are not known anymore, its groups share its minimum, maximum
+        // and average
+        assert_eq!(summary(&window[..4]), [(0, 8, 15); 4]);
+        assert_eq!(summary(&window[8..9]), [(32, 34, 35)]);
+        assert_eq!(
+            summary(&history.window(2, 4)),
+            [(1048, 1050, 1051), (1052, 1054, 1055)]
+        );
+    }
+
+    #[test]
+    fn window_with_zoom_across_the_buckets() {
+        let history = history_of(RECENT_POINTS + 32, RECENT_POINTS as i32 + 32);
+        let window = history.window(1000, 5);
+        // 1056 points make 211 groups, and an oldest one of a point
+        assert_eq!(window.len(), 212);
+        assert_eq!(summary(&window[..1]), [(0, 8, 15)]);
+        // The recent points are grouped from the latest, the last group of the recent points
+        // takes a point of the newest bucket
+        assert_eq!(summary(&window[7..8]), [(16, 32, 35)]);
+        assert_eq!(summary(&window[211..]), [(1051, 1053, 1055)]);
+    }
+
+    #[test]
+    fn window_across_recent_pending_and_buckets() {
+        let history = history_of(RECENT_POINTS + 32, RECENT_POINTS as i32 + 40);
+        assert_eq!(history.pending.map(|pending| pending.count), Some(8));
+        assert_eq!(history.len(), RECENT_POINTS + 40);
+
+        // The latest group takes the recent points, the pending ones and 4 points of the newest
+        // bucket, the oldest group the rest of the buckets
+        let window = history.window(2, RECENT_POINTS + 8 + 4);
+        assert_eq!(summary(&window), [(0, 14, 31), (16, 545, 1063)]);
+        assert_eq!(summary(&history.window(1, 10_000)), [(0, 532, 1063)]);
+    }
+}
@@ -0,0 +1,434 @@
+use serde::Deserialize;
+
+/// Smallest window able to display a widget, borders included
+pub const MIN_WINDOW_HEIGHT: i32 = 4;
+pub const MIN_WINDOW_WIDTH: i32 = 14;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub fn can_display_widget(&self) -> bool {
+        self.height >= MIN_WINDOW_HEIGHT && self.width >= MIN_WINDOW_WIDTH
+    }
+}
+
+/// Space taken by a node along the direction of its parent split
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Size {
+    /// Share of the space left once the fixed sizes are removed
+    Ratio(u32),
+    /// Number of cells
+    Fixed(i32),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Children are placed side by side
+    Horizontal,
+    /// Children are stacked from top to bottom
+    Vertical,
+}
+
+/// Cells of a grid covered by a widget
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GridArea {
+    pub row: usize,
+    pub column: usize,
+    pub row_span: usize,
+    pub column_span: usize,
+}
+
+impl GridArea {
+    /// Whether both areas cover a same cell
+    pub fn overlaps(&self, other: &Self) -> bool {
+        let crosses = |start: usize, span: usize, other_start: usize, other_span: usize| {
+            start < other_start.saturating_add(other_span.max(1))
+                && other_start < start.saturating_add(span.max(1))
+        };
+        crosses(self.row, self.row_span, other.row, other.row_span)
+            && crosses(
+                self.column,
+                self.column_span,
+                other.column,
+                other.column_span,
+            )
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Layout {
+    Widget(Size),
+    Split {
+        direction: Direction,
+        size: Size,
+        children: Vec<Layout>,
+    },
+    /// Rows and columns sized by weight, each widget covering one or more cells
+    Grid {
+        rows: Vec<u32>,
+        columns: Vec<u32>,
+        areas: Vec<GridArea>,
+    },
+}
+
+impl Layout {
+    /// Layout used when a page only lists its widgets: widgets are spread on rows, the last
+    /// rows holding one more widget than the first ones if they cannot be evenly spread
+    pub fn auto(widget_count: usize) -> Self {
+        let mut rows_count = 1;
+        while rows_count * rows_count < widget_count {
+            rows_count += 1;
+        }
+
+        let widgets_per_row = widget_count / rows_count;
+        let rows_with_extra_widget = widget_count % rows_count;
+        let rows = (0..rows_count)
+            .map(|row| {
+                let row_widgets = if row >= rows_count - rows_with_extra_widget {
+                    widgets_per_row + 1
+                } else {
+                    widgets_per_row
+                };
+                Self::Split {
+                    direction: Direction::Horizontal,
+                    size: Size::Ratio(1),
+                    children: vec![Self::Widget(Size::Ratio(1)); row_widgets],
+                }
+            })
+            .filter(|row| !row.is_empty())
+            .collect();
+
+        Self::Split {
+            direction: Direction::Vertical,
+            size: Size::Ratio(1),
+            children: rows,
+        }
+    }
+
+    pub fn size(&self) -> Size {
+        match self {
+            Self::Widget(size) | Self::Split { size, .. } => *size,
+            Self::Grid { .. } => Size::Ratio(1),
+        }
+    }
+
+    pub fn widget_count(&self) -> usize {
+        match self {
+            Self::Widget(_) => 1,
+            Self::Split { children, .. } => children.iter().map(Self::widget_count).sum(),
+            Self::Grid { areas, .. } => areas.len(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.widget_count() == 0
+    }
+
+    /// Compute the area of each widget, in the order they are declared
+    pub fn compute(&self, area: Rect) -> Vec<Rect> {
+        let mut areas = vec![];
+        self.compute_into(area, &mut areas);
+        areas
+    }
+
+    fn compute_into(&self, area: Rect, areas: &mut Vec<Rect>) {
+        match self {
+            Self::Widget(_) => areas.push(area),
+            Self::Split {
+                direction,
+                children,
+                ..
+            } => {
+                let total = match direction {
+                    Direction::Horizontal => area.width,
+                    Direction::Vertical => area.height,
+                };
+                let sizes = split(
+                    total,
+                    &children.iter().map(Self::size).collect::<Vec<Size>>(),
+                );
+
+                let mut offset = 0;
+                for (child, size) in children.iter().zip(sizes) {
+                    let child_area = match direction {
+                        Direction::Horizontal => Rect {
+                            x: area.x + offset,
+                            width: size,
+                            ..area
+                        },
+                        Direction::Vertical => Rect {
+                            y: area.y + offset,
+                            height: size,
+                            ..area
+                        },
+                    };
+                    child.compute_into(child_area, areas);
+                    offset += size;
+                }
+            }
+            Self::Grid {
+                rows,
+                columns,
+                areas: grid_areas,
+            } => {
+                let tracks = |total: i32, weights: &[u32]| {
+                    let sizes = split(
+                        total,
+                        &weights
+                            .iter()
+                            .map(|weight| Size::Ratio(*weight))
+                            .collect::<Vec<Size>>(),
+                    );
+                    // Offset of each track, followed by the end of the last one
+                    let mut offsets = vec![0];
+                    for size in sizes {
+                        offsets.push(offsets[offsets.len() - 1] + size);
+                    }
+                    offsets
+                };
+                let rows = tracks(area.height, rows);
+                let columns = tracks(area.width, columns);
+
+                // Areas outside of the grid are clamped to its last cells, or empty
+                let span = |offsets: &[i32], start: usize, span: usize| {
+                    let last = offsets.len() - 1;
+                    let start = start.min(last);
+                    let end = start.saturating_add(span.max(1)).min(last);
+                    (offsets[start], offsets[end] - offsets[start])
+                };
+                for grid_area in grid_areas {
+                    let (y, height) = span(&rows, grid_area.row, grid_area.row_span);
+                    let (x, width) = span(&columns, grid_area.column, grid_area.column_span);
+                    areas.push(Rect {
+                        x: area.x + x,
+                        y: area.y + y,
+                        width,
+                        height,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Place widgets in a grid of `rows` by `columns` cells. Widgets without a position take the
+/// first free cell, reading rows from left to right
+pub fn place_in_grid(
+    rows: usize,
+    columns: usize,
+    positions: &[(Option<usize>, Option<usize>, usize, usize)],
+) -> Vec<GridArea> {
+    fn occupy(occupied: &mut [Vec<bool>], area: &GridArea) {
+        for row in occupied.iter_mut().skip(area.row).take(area.row_span) {
+            for cell in row.iter_mut().skip(area.column).take(area.column_span) {
+                *cell = true;
+            }
+        }
+    }
+
+    let mut occupied = vec![vec![false; columns]; rows];
+
+    let mut areas: Vec<Option<GridArea>> = positions
+        .iter()
+        .map(|(row, column, row_span, column_span)| {
+            let area = GridArea {
+                row: (*row)?,
+                column: (*column)?,
+                row_span: (*row_span).max(1),
+                column_span: (*column_span).max(1),
+            };
+            occupy(&mut occupied, &area);
+            Some(area)
+        })
+        .collect();
+
+    for (area, (row, column, row_span, column_span)) in areas.iter_mut().zip(positions) {
+        if area.is_some() {
+            continue;
+        }
+        let free_cell = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (row, column)))
+            .filter(|(cell_row, _)| row.is_none_or(|row| row == *cell_row))
+            .filter(|(_, cell_column)| column.is_none_or(|column| column == *cell_column))
+            .find(|(row, column)| !occupied[*row][*column]);
+        // Without a free cell, the widget is put after the last row and stays hidden
+        let (row, column) = free_cell.unwrap_or((rows, 0));
+        let placed = GridArea {
+            row,
+            column,
+            row_span: (*row_span).max(1),
+            column_span: (*column_span).max(1),
+        };
+        occupy(&mut occupied, &placed);
+        *area = Some(placed);
+    }
+
+    areas.into_iter().flatten().collect()
+}
+
+/// Share `total` cells between `sizes`. Fixed sizes are served first, in order, without
+/// exceeding `total`, then the rest is shared between ratios, the rounding leftover going to the
+/// last ones
+pub fn split(total: i32, sizes: &[Size]) -> Vec<i32> {
+    let mut remaining = total.max(0);
+    let mut result: Vec<i32> = sizes
+        .iter()
+        .map(|size| match size {
+            Size::Fixed(cells) => {
+                let cells = (*cells).clamp(0, remaining);
+                remaining -= cells;
+                cells
+            }
+            Size::Ratio(_) => 0,
+        })
+        .collect();
+
+    let ratio_total: u32 = sizes
+        .iter()
+        .map(|size| match size {
+            Size::Ratio(ratio) => *ratio,
+            Size::Fixed(_) => 0,
+        })
+        .sum();
+    if ratio_total == 0 {
+        return result;
+    }
+
+    let mut shared = 0;
+    for (cells, size) in result.iter_mut().zip(sizes) {
+        if let Size::Ratio(ratio) = size {
+            *cells = (i64::from(remaining) * i64::from(*ratio) / i64::from(ratio_total)) as i32;
+            shared += *cells;
+        }
+    }
+
+    let mut leftover = remaining - shared;
+    for (cells, size) in result.iter_mut().zip(sizes).rev() {
+        if leftover == 0 {
+            break;
+        }
+        if matches!(size, Size::Ratio(ratio) if *ratio > 0) {
+            *cells += 1;
+            leftover -= 1;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(row: usize, column: usize, row_span: usize, column_span: usize) -> GridArea {
+        GridArea {
+            row,
+            column,
+            row_span,
+            column_span,
+        }
+    }
+
+    #[test]
+    fn split_serves_fixed_sizes_first() {
+        assert_eq!(
+            split(10, &[Size::Fixed(3), Size::Ratio(1), Size::Ratio(1)]),
+            vec![3, 3, 4]
+        );
+        assert_eq!(
+            split(5, &[Size::Fixed(4), Size::Fixed(4), Size::Ratio(1)]),
+            vec![4, 1, 0]
+        );
+        assert_eq!(split(10, &[Size::Ratio(1), Size::Ratio(2)]), vec![3, 7]);
+        assert_eq!(split(-3, &[Size::Fixed(2), Size::Ratio(1)]), vec![0, 0]);
+        assert_eq!(split(10, &[Size::Ratio(0), Size::Fixed(2)]), vec![0, 2]);
+    }
+
+    #[test]
+    fn auto_layout_spreads_widgets_on_rows() {
+        let row_lengths = |layout: &Layout| match layout {
+            Layout::Split { children, .. } => children
+                .iter()
+                .map(Layout::widget_count)
+                .collect::<Vec<usize>>(),
+            Layout::Widget(_) | Layout::Grid { .. } => vec![],
+        };
+        assert_eq!(row_lengths(&Layout::auto(0)), Vec::<usize>::new());
+        assert_eq!(row_lengths(&Layout::auto(1)), vec![1]);
+        assert_eq!(row_lengths(&Layout::auto(3)), vec![1, 2]);
+        assert_eq!(row_lengths(&Layout::auto(4)), vec![2, 2]);
+        assert_eq!(row_lengths(&Layout::auto(7)), vec![2, 2, 3]);
+
+        let areas = Layout::auto(3).compute(Rect {
+            x: 0,
+            y: 0,
+            width: 80,
+            height: 24,
+        });
+        assert_eq!(
+            areas,
+            vec![
+                Rect {
+                    x: 0,
+                    y: 0,
+                    width: 80,
+                    height: 12,
+                },
+                Rect {
+                    x: 0,
+                    y: 12,
+                    width: 40,
+                    height: 12,
+                },
+                Rect {
+                    x: 40,
+                    y: 12,
+                    width: 40,
+                    height: 12,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn place_in_grid_fills_free_cells() {
+        let areas = place_in_grid(
+            2,
+            2,
+            &[
+                (None, None, 1, 1),
+                (Some(0), Some(0), 1, 2),
+                (None, Some(1), 1, 1),
+                (Some(1), None, 1, 1),
+            ],
+        );
+        assert_eq!(
+            areas,
+            vec![
+                area(1, 0, 1, 1),
+                area(0, 0, 1, 2),
+                area(1, 1, 1, 1),
+                area(2, 0, 1, 1)
+            ]
+        );
+
+        assert_eq!(
+            place_in_grid(1, 1, &[(Some(0), Some(0), 0, 0), (None, None, 1, 1)]),
+            vec![area(0, 0, 1, 1), area(1, 0, 1, 1)]
+        );
+    }
+
+    #[test]
+    fn grid_areas_overlap() {
+        assert!(area(0, 0, 1, 1).overlaps(&area(0, 0, 1, 1)));
+        assert!(area(0, 0, 2, 1).overlaps(&area(1, 0, 1, 2)));
+        assert!(!area(0, 0, 1, 2).overlaps(&area(1, 0, 1, 2)));
+        assert!(!area(0, 0, 2, 1).overlaps(&area(0, 1, 2, 1)));
+    }
+}
@@ -18,11 +18,9 @@
     clippy::exhaustive_structs,
     clippy::single_char_lifetime_names,
     clippy::integer_division,
-    clippy::separated_literal_suffix,
-    clippy::else_if_without_else,
     clippy::indexing_slicing,
     clippy::cast_possible_truncation,
-    clippy::integer_arithmetic,
+    clippy::arithmetic_side_effects,
     clippy::default_numeric_fallback,
     clippy::cast_possible_wrap,
     clippy::cast_sign_loss,
@@ -39,10 +37,21 @@
     clippy::wildcard_imports,
     clippy::single_match_else,
     clippy::single_match,
-    clippy::let_underscore_drop,
+    let_underscore_drop,
     clippy::expect_used,
     clippy::suboptimal_flops,
     clippy::redundant_else
 )]
 
+pub mod cli;
+pub mod config;
+pub mod exporter;
+pub mod history;
+pub mod layout;
+pub mod metrics;
+pub mod recording;
+pub mod theme;
 pub mod window;
+
+#[cfg(test)]
+mod test_files;
@@ -2,422 +2,2544 @@ use chrono::Timelike;
 use ncurses::*;
 use rtop_dev::components::listview::Ordering;
 use rtop_dev::{components, widget};
-use rtop_rs::window;
-use serde::Deserialize;
-use sysinfo::{ProcessExt, CpuExt, SystemExt};
-
-type WidgetInitializer = fn() -> (Box<dyn widget::Widget>, bool);
-type WidgetInitializerResult<'a> =
-    std::result::Result<libloading::Symbol<'a, WidgetInitializer>, libloading::Error>;
-
-fn default_pages() -> Vec<Vec<String>> {
-    vec![vec![
-        String::from("cpu_chart"),
-        String::from("memory_chart"),
-        String::from("process_list"),
-    ]]
-}
-#[derive(Deserialize)]
-struct Option {
-    #[serde(default = "default_pages")]
-    pages: Vec<Vec<String>>,
-    #[serde(default)]
-    plugins: Vec<LibOption>,
-}
-#[derive(Deserialize)]
-struct LibOption {
-    #[serde(default)]
-    path: String,
-    #[serde(default)]
-    name: String,
+use rtop_rs::{cli, config, exporter, history, layout, metrics, recording, theme, window};
+use sysinfo::{ComponentExt, CpuExt, DiskExt, NetworkExt, PidExt, ProcessExt, SystemExt, UserExt};
+
+/// Initializer of a built-in widget, which takes its samples from the source of the interface and
+/// sets the `TextCapture` while it takes every key as text
+type WidgetInitializer = fn(
+    &serde_json::Value,
+    &recording::SampleSource,
+    &TextCapture,
+) -> (Box<dyn widget::Widget>, bool);
+/// Initializer exported by plugins as `init_<widget>`, which does not take options
+type PluginInitializerV1 = fn() -> (Box<dyn widget::Widget>, bool);
+/// Initializer exported by plugins as `init_v2_<widget>`, which receives the options of the widget
+/// serialized as a JSON object
+type PluginInitializerV2 = fn(&str) -> (Box<dyn widget::Widget>, bool);
+type PluginSymbolResult<'a, T> = std::result::Result<libloading::Symbol<'a, T>, libloading::Error>;
+/// Set by a widget while it takes every key as text, like the search prompt of the process list,
+/// so that the keys of the interface such as `q` are typed into it instead
+#[derive(Clone, Default)]
+struct TextCapture(std::sync::Arc<std::sync::atomic::AtomicBool>);
+
+impl TextCapture {
+    fn set(&self, capturing: bool) {
+        self.0
+            .store(capturing, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    fn is_capturing(&self) -> bool {
+        self.0.load(std::sync::atomic::Ordering::Relaxed)
+    }
 }
 
 struct MemoryUsage {
     sysinfo: sysinfo::System,
-    data: Vec<i32>,
+    source: recording::SampleSource,
+    /// Restarts of the charts seen by the widget, see `SampleSource::charts_restarted`
+    restarts: u64,
+    data: history::History,
     chart: components::chart::Chart,
+    swap_data: history::History,
+    swap_chart: components::chart::Chart,
+    /// Points of the history per point of the charts
+    zoom: usize,
+    /// Points shown by the charts at the last display
+    visible_points: usize,
+    memory: recording::MemoryFrame,
+    color: std::option::Option<String>,
+    swap_color: std::option::Option<String>,
 }
 struct PluginError {
     message: String,
 }
 struct CpuUsage {
     sysinfo: sysinfo::System,
-    data: Vec<i32>,
+    source: recording::SampleSource,
+    /// Restarts of the charts seen by the widget, see `SampleSource::charts_restarted`
+    restarts: u64,
+    data: history::History,
+    chart: components::chart::Chart,
+    /// Points of the history per point of the chart
+    zoom: usize,
+    /// Points shown by the chart at the last display
+    visible_points: usize,
+    /// Highest usage in the visible time window, shown when zoomed out
+    visible_peak: std::option::Option<i32>,
+    color: std::option::Option<String>,
+}
+struct NetworkUsage {
+    sysinfo: sysinfo::System,
+    source: recording::SampleSource,
+    /// Restarts of the charts seen by the widget, see `SampleSource::charts_restarted`
+    restarts: u64,
+    /// History of each interface, the "All" entry is the sum of every interface
+    history: std::collections::BTreeMap<String, NetworkHistory>,
+    /// Points kept by the history of each interface
+    retention: usize,
+    selected: usize,
+    chart: components::chart::Chart,
+    /// Points of the history per point of the charts
+    zoom: usize,
+    /// Points shown by the charts at the last display
+    visible_points: usize,
+    last_update: std::time::Instant,
+    refresh_progress: usize,
+}
+struct NetworkHistory {
+    received: history::History,
+    transmitted: history::History,
+    total_received: u64,
+    total_transmitted: u64,
+}
+struct DiskUsage {
+    sysinfo: sysinfo::System,
+    source: recording::SampleSource,
+    disks: Vec<recording::DiskFrame>,
+    refresh_progress: usize,
+}
+struct DiskIo {
+    sysinfo: sysinfo::System,
+    source: recording::SampleSource,
+    /// Restarts of the charts seen by the widget, see `SampleSource::charts_restarted`
+    restarts: u64,
+    read: history::History,
+    written: history::History,
+    chart: components::chart::Chart,
+    /// Points of the history per point of the charts
+    zoom: usize,
+    /// Points shown by the charts at the last display
+    visible_points: usize,
+    /// Processes doing I/O, from the busiest
+    top_processes: Vec<recording::ProcessIoFrame>,
+    last_update: std::time::Instant,
+    refresh_progress: usize,
+}
+struct Temperatures {
+    sysinfo: sysinfo::System,
+    source: recording::SampleSource,
+    /// Restarts of the charts seen by the widget, see `SampleSource::charts_restarted`
+    restarts: u64,
+    sensors: Vec<recording::SensorFrame>,
+    /// Temperature history of each sensor, by label and occurrence of the label so that it
+    /// follows the sensor when the sensors change, see `Temperatures::sensor_key`
+    data: std::collections::HashMap<(String, usize), history::History>,
+    /// Points kept by the history of each sensor
+    retention: usize,
+    selected: usize,
+    chart: components::chart::Chart,
+    /// Points of the history per point of the chart
+    zoom: usize,
+    /// Points shown by the chart at the last display
+    visible_points: usize,
+}
+struct CpuCores {
+    sysinfo: sysinfo::System,
+    source: recording::SampleSource,
+    /// Restarts of the charts seen by the widget, see `SampleSource::charts_restarted`
+    restarts: u64,
+    /// Usage history of each CPU
+    data: Vec<history::History>,
+    /// Points kept by the history of each CPU
+    retention: usize,
+    /// Frequency of each CPU, in MHz
+    frequencies: Vec<u64>,
     chart: components::chart::Chart,
-    last_cpu_usage: f32,
+    /// Points of the history per point of the charts
+    zoom: usize,
+    /// Points shown by the charts at the last display
+    visible_points: usize,
 }
 struct ProcessList {
     sysinfo: sysinfo::System,
+    source: recording::SampleSource,
+    /// Processes grouped by name, the only ones known in a replay
+    groups: Vec<metrics::ProcessGroup>,
     data: Vec<components::listview::ListItem>,
     chart: components::listview::ListView,
     refresh_progress: usize,
-    kill_process_security: bool,
+    signal_prompt: SignalPrompt,
+    status: std::option::Option<String>,
+    search: std::option::Option<ProcessSearch>,
+    /// Set while the search prompt is open
+    search_editing: TextCapture,
+    mode: ProcessListMode,
+    sort_key: String,
+    sort_inversed: bool,
+    collapsed_processes: std::collections::HashSet<sysinfo::Pid>,
+    /// Columns of the per-process mode chosen in the config
+    columns: Vec<String>,
+    /// Columns of the current mode which fit in the widget
+    visible_columns: Vec<String>,
+    /// Number of updates between two refreshes of the processes
+    refresh_ticks: usize,
+}
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum ProcessListMode {
+    Grouped,
+    Tree,
+    PerProcess,
 }
 
-impl widget::Widget for ProcessList {
-    fn on_update(&mut self) {
-        self.refresh_progress += 1;
-        if self.refresh_progress == 7 {
-            let mut process_done = vec![];
-            let mut new_process_list = vec![];
-            let physical_core_count = self.sysinfo.physical_core_count().unwrap();
+struct ProcessSearch {
+    query: String,
+    regex: regex::Regex,
+}
 
-            for process in self.sysinfo.processes().values() {
-                if process_done.contains(&process.name()) {
-                    continue;
-                }
-                process_done.push(process.name());
+impl ProcessSearch {
+    /// Case insensitive search, the query is used as a literal text if it is not a valid regex
+    fn new(query: &str) -> Self {
+        let regex = regex::RegexBuilder::new(query)
+            .case_insensitive(true)
+            .build()
+            .or_else(|_| {
+                regex::RegexBuilder::new(&regex::escape(query))
+                    .case_insensitive(true)
+                    .build()
+            })
+            .unwrap();
 
-                let mut process_data = std::collections::HashMap::new();
-                let mut total_cpu = 0.;
-                let mut total_memory: u64 = 0;
-                let mut count: i32 = 0;
-                for sub_proc in self.sysinfo.processes_by_exact_name(process.name()) {
-                    count += 1;
-                    total_memory += sub_proc.memory();
-                    total_cpu += sub_proc.cpu_usage();
-                }
-                process_data.insert(
-                    String::from("CPU %"),
-                    format!("{:.1}", (total_cpu / physical_core_count as f32)),
-                );
-                process_data.insert(String::from("Count"), format!("{}", count));
-                process_data.insert(
-                    String::from("Memory %"),
-                    format!(
-                        "{:.1}",
-                        (total_memory as f32 * 100. / self.sysinfo.total_memory() as f32)
-                    ),
-                );
+        Self {
+            query: String::from(query),
+            regex,
+        }
+    }
 
-                new_process_list.push(components::listview::ListItem::new(
-                    process.name(),
-                    &process_data,
-                ));
-            }
+    fn matches(&self, process: &sysinfo::Process) -> bool {
+        self.regex.is_match(process.name()) || self.regex.is_match(&process.cmd().join(" "))
+    }
+}
 
-            self.data = new_process_list;
-            self.sysinfo.refresh_processes();
-            self.refresh_progress = 0;
-        }
+enum SignalPrompt {
+    Closed,
+    Picking {
+        pids: Vec<sysinfo::Pid>,
+        selected: usize,
+    },
+    Confirming {
+        pids: Vec<sysinfo::Pid>,
+        signal: usize,
+    },
+}
+
+/// Signals offered by the signal picker: name, number and description
+const SIGNALS: [(&str, libc::c_int, &str); 8] = [
+    ("SIGTERM", libc::SIGTERM, "Terminate"),
+    ("SIGKILL", libc::SIGKILL, "Kill"),
+    ("SIGHUP", libc::SIGHUP, "Hangup"),
+    ("SIGINT", libc::SIGINT, "Interrupt"),
+    ("SIGSTOP", libc::SIGSTOP, "Stop"),
+    ("SIGCONT", libc::SIGCONT, "Continue"),
+    ("SIGUSR1", libc::SIGUSR1, "User defined 1"),
+    ("SIGUSR2", libc::SIGUSR2, "User defined 2"),
+];
+
+/// Columns of the per-process mode, in display order
+const PROCESS_COLUMNS: [&str; 9] = [
+    "PID", "User", "State", "Nice", "Start", "Elapsed", "Threads", "CPU %", "Memory %",
+];
+/// Columns hidden first when the per-process mode does not fit in the widget, the PID is always
+/// shown
+const PROCESS_COLUMNS_HIDE_ORDER: [&str; 8] = [
+    "Start", "Elapsed", "Threads", "Nice", "State", "User", "Memory %", "CPU %",
+];
+/// Columns of the grouped mode, in display order
+const GROUP_COLUMNS: [&str; 3] = ["CPU %", "Count", "Memory %"];
+/// Columns hidden first when the grouped mode does not fit in the widget
+const GROUP_COLUMNS_HIDE_ORDER: [&str; 3] = ["Count", "Memory %", "CPU %"];
+/// Columns of the tree mode, in display order
+const TREE_COLUMNS: [&str; 4] = ["PID", "CPU %", "Count", "Memory %"];
+/// Columns hidden first when the tree mode does not fit in the widget
+const TREE_COLUMNS_HIDE_ORDER: [&str; 4] = ["Count", "PID", "Memory %", "CPU %"];
+/// Width of the name or command column below which other columns are hidden
+const PROCESS_NAME_MIN_WIDTH: usize = 10;
+/// Markup of the `ListView` around the header of the column sorted in the default order, then
+/// in the inversed order
+const SORT_MARKUP: [&str; 2] = ["[[EFFECT_BOLD]]", "[[EFFECT_ITALIC]]"];
+/// Columns of the grouped and tree modes which can be used to sort the processes
+const PROCESS_GROUP_COLUMNS: [&str; 4] = ["Name", "CPU %", "Count", "Memory %"];
+#[derive(Default, Clone, Copy)]
+struct ProcessUsage {
+    cpu: f32,
+    memory: u64,
+    count: i32,
+}
+struct ProcessTree {
+    roots: Vec<sysinfo::Pid>,
+    children: std::collections::HashMap<sysinfo::Pid, Vec<sysinfo::Pid>>,
+    usage: std::collections::HashMap<sysinfo::Pid, ProcessUsage>,
+}
+
+impl ProcessList {
+    fn update_data(&mut self) {
+        self.data = match self.mode {
+            ProcessListMode::Grouped => self.grouped_items(),
+            ProcessListMode::Tree => {
+                let processes = self.sysinfo.processes();
+                self.collapsed_processes
+                    .retain(|pid| processes.contains_key(pid));
+                self.tree_items()
+            }
+            ProcessListMode::PerProcess => self.per_process_items(),
+        };
     }
 
-    fn display(&mut self, h: i32, w: i32) -> String {
-        self.chart.resize(h, w);
-        self.chart.update_items(&self.data);
-        self.chart.display()
+    fn process_matches(&self, process: &sysinfo::Process) -> bool {
+        self.search
+            .as_ref()
+            .is_none_or(|search| search.matches(process))
     }
 
-    fn on_input(&mut self, key: String) {
-        if key == "KEY_DOWN" || key == "k" {
-            self.chart.next();
-        } else if key == "KEY_UP" || key == "j" {
-            self.chart.previous();
-        } else if key == "g" {
-            self.chart.to_first();
-        } else if key == "G" {
-            self.chart.to_last();
-        } else if key == "m" {
-            self.chart.sort_by(
-                std::option::Option::from(String::from("Memory %")),
-                std::option::Option::from(Ordering::Default),
+    fn per_process_items(&self) -> Vec<components::listview::ListItem> {
+        let physical_core_count = self.sysinfo.physical_core_count().unwrap();
+        let mut items = vec![];
+
+        for (pid, process) in self.sysinfo.processes() {
+            if !self.process_matches(process) {
+                continue;
+            }
+
+            let mut process_data = std::collections::HashMap::new();
+            process_data.insert(String::from("PID"), pid.to_string());
+            process_data.insert(
+                String::from("User"),
+                process
+                    .user_id()
+                    .map(|uid| {
+                        self.sysinfo
+                            .get_user_by_id(uid)
+                            .map_or_else(|| uid.to_string(), |user| String::from(user.name()))
+                    })
+                    .unwrap_or_else(|| String::from("?")),
             );
-        } else if key == "c" {
-            self.chart.sort_by(
-                std::option::Option::from(String::from("CPU %")),
-                std::option::Option::from(Ordering::Default),
+            process_data.insert(String::from("State"), process.status().to_string());
+            process_data.insert(String::from("Nice"), niceness_string(*pid));
+            process_data.insert(
+                String::from("Start"),
+                chrono::TimeZone::timestamp_opt(&chrono::Local, process.start_time() as i64, 0)
+                    .single()
+                    .map_or_else(
+                        || String::from("?"),
+                        |start| start.format("%m-%d %H:%M").to_string(),
+                    ),
             );
-        } else if key == "n" {
-            self.chart.sort_by(
-                std::option::Option::from(String::from("Name")),
-                std::option::Option::from(Ordering::Inversed),
+            let run_time = process.run_time();
+            process_data.insert(
+                String::from("Elapsed"),
+                format!(
+                    "{}:{:02}:{:02}",
+                    run_time / 3600,
+                    run_time / 60 % 60,
+                    run_time % 60
+                ),
             );
-        } else if key == "C" {
-            self.chart.sort_by(
-                std::option::Option::from(String::from("Count")),
-                std::option::Option::from(Ordering::Default),
+            process_data.insert(
+                String::from("Threads"),
+                format!("{}", process.tasks.len().max(1)),
             );
-        } else if key == "d" {
-            if self.kill_process_security {
-                let item = self.chart.select();
-                for proc in self.sysinfo.processes_by_exact_name(&item.name) {
-                    proc.kill();
-                }
-            }
-            self.kill_process_security = !self.kill_process_security;
-        }
-        if key != "d" && self.kill_process_security {
-            self.kill_process_security = false;
+            process_data.insert(
+                String::from("CPU %"),
+                format!("{:.1}", (process.cpu_usage() / physical_core_count as f32)),
+            );
+            process_data.insert(
+                String::from("Memory %"),
+                format!(
+                    "{:.1}",
+                    (process.memory() as f32 * 100. / self.sysinfo.total_memory() as f32)
+                ),
+            );
+
+            let command = if process.cmd().is_empty() {
+                format!("[{}]", process.name())
+            } else {
+                process.cmd().join(" ")
+            };
+            items.push(components::listview::ListItem::new(&command, &process_data));
         }
-    }
 
-    fn title(&mut self) -> std::option::Option<String> {
-        Some(format!("[{}] Processes", self.data.len()))
+        items
     }
-}
-impl widget::Widget for CpuUsage {
-    fn on_update(&mut self) {
-        self.sysinfo.refresh_cpu();
-        self.data.push(
-            ((self.sysinfo.global_cpu_info().cpu_usage() + self.last_cpu_usage) / 2.) as i32,
-        );
+
+    /// Columns of the current mode in display order, and the order in which they are hidden
+    /// when the table does not fit in the widget
+    fn mode_columns(&self) -> (Vec<String>, &'static [&'static str]) {
+        match self.mode {
+            ProcessListMode::Grouped => (
+                GROUP_COLUMNS.into_iter().map(String::from).collect(),
+                &GROUP_COLUMNS_HIDE_ORDER,
+            ),
+            ProcessListMode::Tree => (
+                TREE_COLUMNS.into_iter().map(String::from).collect(),
+                &TREE_COLUMNS_HIDE_ORDER,
+            ),
+            ProcessListMode::PerProcess => (self.columns.clone(), &PROCESS_COLUMNS_HIDE_ORDER),
+        }
     }
 
-    fn display(&mut self, h: i32, w: i32) -> String {
-        self.chart.resize(w, h);
-        self.chart.display(&self.data)
+    fn primary_key(&self) -> &'static str {
+        if self.mode == ProcessListMode::PerProcess {
+            "Command"
+        } else {
+            "Name"
+        }
     }
 
-    fn title(&mut self) -> std::option::Option<String> {
-        Some(String::from("CPU Usage"))
+    /// Hide the less useful columns of the current mode until the table fits in `width`, or
+    /// all the columns which can be are hidden
+    fn fit_columns(&mut self, width: usize) {
+        let (mut columns, hide_order) = self.mode_columns();
+        for hidden_column in hide_order {
+            if self.columns_width(&columns) + PROCESS_NAME_MIN_WIDTH <= width {
+                break;
+            }
+            columns.retain(|column| column != hidden_column);
+        }
+
+        if columns != self.visible_columns {
+            self.visible_columns = columns;
+            self.chart = self.create_listview();
+        }
     }
-}
-impl widget::Widget for MemoryUsage {
-    fn display(&mut self, h: i32, w: i32) -> String {
-        self.chart.resize(w, h);
-        self.chart.display(&self.data)
+
+    /// Width taken by `columns` in the `ListView`, including the sort markup that the
+    /// `ListView` wrongly counts as visible when a secondary column is sorted
+    fn columns_width<T: AsRef<str>>(&self, columns: &[T]) -> usize {
+        // The tree is not sorted by the `ListView`
+        let sort_markup_width = if self.mode != ProcessListMode::Tree
+            && columns
+                .iter()
+                .any(|column| column.as_ref() == self.sort_key)
+        {
+            2 * SORT_MARKUP[usize::from(self.sort_inversed)].len()
+        } else {
+            0
+        };
+
+        columns
+            .iter()
+            .map(|column| {
+                self.data
+                    .iter()
+                    .filter_map(|item| item.data.get(column.as_ref()))
+                    .map(String::len)
+                    .fold(column.as_ref().len(), usize::max)
+                    + 2
+            })
+            .sum::<usize>()
+            + sort_markup_width
     }
 
-    fn on_update(&mut self) {
-        self.sysinfo.refresh_memory();
+    /// Items of the current mode, with the names cut to the free space of the row minus one
+    /// cell to keep them apart from the first column
+    fn fitted_items(&self, width: usize) -> Vec<components::listview::ListItem> {
+        let name_width = width.saturating_sub(self.columns_width(&self.visible_columns) + 1);
         self.data
-            .push((self.sysinfo.used_memory() * 100 / self.sysinfo.total_memory()) as i32);
+            .iter()
+            .map(|item| {
+                components::listview::ListItem::new(
+                    &item.name.chars().take(name_width).collect::<String>(),
+                    &item.data,
+                )
+            })
+            .collect()
     }
 
-    fn title(&mut self) -> std::option::Option<String> {
-        Some(String::from("Memory"))
-    }
-}
-impl widget::Widget for PluginError {
-    fn display(&mut self, h: i32, w: i32) -> String {
-        let error_message = format!("An error occured: {}", self.message);
-        format!(
-            "{}{}{}",
-            String::from("\n").repeat((h / 2) as usize),
-            String::from(" ").repeat((w / 2) as usize - error_message.len() / 2),
-            error_message
-        )
+    fn grouped_items(&self) -> Vec<components::listview::ListItem> {
+        let processes = self.sysinfo.processes();
+        self.groups
+            .iter()
+            .filter(|group| {
+                // Replayed groups have no PIDs, only their name is searched
+                if group.pids.is_empty() {
+                    return self
+                        .search
+                        .as_ref()
+                        .is_none_or(|search| search.regex.is_match(&group.name));
+                }
+                group
+                    .pids
+                    .iter()
+                    .filter_map(|pid| processes.get(pid))
+                    .any(|process| self.process_matches(process))
+            })
+            .map(|group| {
+                let mut process_data = std::collections::HashMap::new();
+                // Not a column, the name of the item may be cut to fit in the widget
+                process_data.insert(String::from("Name"), String::from(&group.name));
+                process_data.insert(String::from("CPU %"), format!("{:.1}", group.cpu_percent));
+                process_data.insert(String::from("Count"), format!("{}", group.count));
+                process_data.insert(
+                    String::from("Memory %"),
+                    format!("{:.1}", group.memory_percent),
+                );
+                components::listview::ListItem::new(&group.name, &process_data)
+            })
+            .collect()
     }
-}
 
-struct ScreenWidget {
-    plugin: Box<dyn widget::Widget>,
-    name: String,
-}
-struct Page {
-    widgets: Vec<ScreenWidget>,
-    focusable_widgets: Vec<usize>,
-}
-unsafe impl Send for Page {}
+    fn tree_items(&self) -> Vec<components::listview::ListItem> {
+        let tree = self.build_tree();
+        let mut items = vec![];
+        self.push_tree_items(&tree, &tree.roots, "", true, &mut items);
+        items
+    }
 
-#[tokio::main]
-async fn main() {
-    let default_panic = std::panic::take_hook(); // Handle error, work only with builtin-plugin
-    std::panic::set_hook(Box::new(move |info| {
-        // TODO: Make this work for plugin
-        curs_set(ncurses::CURSOR_VISIBILITY::CURSOR_VISIBLE);
-        endwin();
-        default_panic(info);
-        std::process::exit(1);
-    }));
+    fn build_tree(&self) -> ProcessTree {
+        let processes = self.sysinfo.processes();
+        let mut tree = ProcessTree {
+            roots: vec![],
+            children: std::collections::HashMap::new(),
+            usage: std::collections::HashMap::new(),
+        };
 
-    let option: Option = serde_json::from_str(
-        &std::fs::read_to_string(format!(
-            "{}/.config/rtop/config.json",
-            home::home_dir().unwrap().display()
-        ))
-        .unwrap_or_else(|_| String::from("{}")),
-    )
-    .unwrap();
-
-    let mut plugins = std::collections::HashMap::new(); // 0: name  1: dylib
-    for plugin in option.plugins {
-        unsafe {
-            let plugin_in_load = libloading::Library::new(String::from(&plugin.path));
-            if let Ok(plugin_in_load) = plugin_in_load {
-                plugins.insert(plugin.name, plugin_in_load);
-            } else {
-                continue;
+        for (pid, process) in processes {
+            match process.parent() {
+                Some(parent) if parent != *pid && processes.contains_key(&parent) => {
+                    tree.children.entry(parent).or_default().push(*pid);
+                }
+                _ => tree.roots.push(*pid),
             }
         }
-    }
 
-    let mut builtin_addon: std::collections::HashMap<String, WidgetInitializer> =
-        std::collections::HashMap::new();
-    builtin_addon.insert(String::from("memory_chart"), init_memory_plugin);
-    builtin_addon.insert(String::from("cpu_chart"), init_cpuusage_plugin);
-    builtin_addon.insert(String::from("process_list"), init_process_plugin);
+        for root in tree.roots.clone() {
+            compute_subtree_usage(processes, &tree.children, &mut tree.usage, root);
+        }
 
-    let mut current_page_number = 1;
-    let sysinfo = sysinfo::System::new_all();
-    let mut current_widget = 1;
+        // Only keep the matching processes and their ancestors
+        if self.search.is_some() {
+            let mut visible = std::collections::HashSet::new();
+            for (pid, process) in processes {
+                if !self.process_matches(process) {
+                    continue;
+                }
+                let mut current = Some(*pid);
+                while let Some(ancestor) = current {
+                    if !visible.insert(ancestor) {
+                        break;
+                    }
+                    current = processes.get(&ancestor).and_then(ProcessExt::parent);
+                }
+            }
 
-    let locale = setlocale(LcCategory::all, "");
-    if !locale.contains("UTF-8") {
-        println!("You need to have a terminal that support UTF-8");
-        return;
-    }
+            tree.roots.retain(|pid| visible.contains(pid));
+            for children in tree.children.values_mut() {
+                children.retain(|pid| visible.contains(pid));
+            }
+            tree.children.retain(|_, children| !children.is_empty());
+        }
 
-    //Initialize Ncurses
-    let term = initscr();
-    keypad(term, true);
-    start_color();
-    use_default_colors();
-    let mut height = 0;
-    let mut width = 0;
-    getmaxyx(term, &mut height, &mut width);
-    curs_set(ncurses::CURSOR_VISIBILITY::CURSOR_INVISIBLE);
+        let sort_key = self.sort_key.as_str();
+        let usage = &tree.usage;
+        let sort_siblings = |pids: &mut Vec<sysinfo::Pid>| {
+            pids.sort_by(|a, b| {
+                let (usage_a, usage_b) = (usage[a], usage[b]);
+                match sort_key {
+                    "CPU %" => usage_b.cpu.total_cmp(&usage_a.cpu),
+                    "Memory %" => usage_b.memory.cmp(&usage_a.memory),
+                    "Count" => usage_b.count.cmp(&usage_a.count),
+                    "Name" => processes[a]
+                        .name()
+                        .to_lowercase()
+                        .cmp(&processes[b].name().to_lowercase()),
+                    _ => a.cmp(b),
+                }
+                .then_with(|| a.cmp(b))
+            });
+        };
+        sort_siblings(&mut tree.roots);
+        for children in tree.children.values_mut() {
+            sort_siblings(children);
+        }
 
-    timeout(334);
-    noecho();
+        tree
+    }
 
-    init_pair(1, COLOR_RED, -1);
-    init_pair(2, COLOR_GREEN, -1);
-    init_pair(3, COLOR_YELLOW, -1);
-    init_pair(4, COLOR_BLUE, -1);
-    init_pair(5, COLOR_MAGENTA, -1);
-    init_pair(6, COLOR_CYAN, -1);
-    init_pair(7, COLOR_WHITE, -1);
-    init_pair(8, COLOR_BLACK, -1);
+    fn push_tree_items(
+        &self,
+        tree: &ProcessTree,
+        pids: &[sysinfo::Pid],
+        prefix: &str,
+        is_root: bool,
+        items: &mut Vec<components::listview::ListItem>,
+    ) {
+        let physical_core_count = self.sysinfo.physical_core_count().unwrap();
 
-    let pages: std::sync::Arc<tokio::sync::Mutex<std::vec::Vec<Page>>> =
-        std::sync::Arc::new(tokio::sync::Mutex::new(vec![]));
-    let pages_mutex = std::sync::Arc::clone(&pages);
+        for (i, pid) in pids.iter().enumerate() {
+            let (branch, child_prefix) = if is_root {
+                (String::new(), String::new())
+            } else if i + 1 == pids.len() {
+                (format!("{}└─ ", prefix), format!("{}   ", prefix))
+            } else {
+                (format!("{}├─ ", prefix), format!("{}│  ", prefix))
+            };
 
-    for page in option.pages {
-        if page.len() > 4 {
-            pages.lock().await.push(Page {
-                widgets: vec![ScreenWidget {
-                    name: String::from("Error"),
-                    plugin: Box::new(PluginError {
-                        message: String::from("You cannot have more than 4 widgets per pages"),
-                    }),
-                }],
-                focusable_widgets: vec![],
-            });
-        } else if page.is_empty() {
-            pages.lock().await.push(Page {
-                widgets: vec![ScreenWidget {
-                    name: String::from("Error"),
-                    plugin: Box::new(PluginError {
-                        message: String::from("You must add a widget to this page"),
-                    }),
-                }],
-                focusable_widgets: vec![],
-            });
-        } else {
-            let mut i = 0;
-            let mut pages_widgets = vec![];
-            let mut focusable_widgets = vec![];
-
-            for widget in page {
-                i += 1;
-                if builtin_addon.contains_key(&widget) {
-                    let mut tmp = builtin_addon[&widget]();
-                    if tmp.1 {
-                        focusable_widgets.push(i);
-                    }
-                    tmp.0.init();
-                    pages_widgets.push(ScreenWidget {
-                        name: String::from(&widget),
-                        plugin: tmp.0,
-                    });
-                } else {
-                    let tmp = &widget.split('.').collect::<Vec<&str>>();
-                    let plugin = plugins.get(tmp[0]);
+            let children = tree.children.get(pid);
+            let collapsed = self.collapsed_processes.contains(pid);
+            let fold_marker = match children {
+                Some(_) if collapsed => "[+] ",
+                Some(_) => "[-] ",
+                None => "",
+            };
+
+            let usage = tree.usage[pid];
+            let mut process_data = std::collections::HashMap::new();
+            process_data.insert(String::from("PID"), pid.to_string());
+            process_data.insert(
+                String::from("CPU %"),
+                format!("{:.1}", (usage.cpu / physical_core_count as f32)),
+            );
+            process_data.insert(String::from("Count"), format!("{}", usage.count));
+            process_data.insert(
+                String::from("Memory %"),
+                format!(
+                    "{:.1}",
+                    (usage.memory as f32 * 100. / self.sysinfo.total_memory() as f32)
+                ),
+            );
 
-                    if let Some(plugin) = plugin {
-                        let initializer: WidgetInitializerResult =
-                            unsafe { plugin.get(format!("init_{}", tmp[1]).as_bytes()) };
+            items.push(components::listview::ListItem::new(
+                &format!(
+                    "{}{}{}",
+                    branch,
+                    fold_marker,
+                    self.sysinfo.processes()[pid].name()
+                ),
+                &process_data,
+            ));
 
-                        if let Ok(initializer) = initializer {
-                            let mut created_widget = initializer();
-                            if created_widget.1 {
-                                focusable_widgets.push(i);
-                            }
-                            created_widget.0.init();
-                            pages_widgets.push(ScreenWidget {
-                                name: widget,
-                                plugin: created_widget.0,
-                            });
-                        } else {
-                            pages_widgets.push(ScreenWidget {
-                                name: String::from("Error"),
-                                plugin: Box::new(PluginError {
-                                    message: format!(
-                                        "Unknow widget {} in plugin {}",
-                                        tmp[1], tmp[0]
-                                    ),
-                                }),
-                            });
-                        }
-                    } else {
-                        pages_widgets.push(ScreenWidget {
-                            name: String::from("Error"),
-                            plugin: Box::new(PluginError {
-                                message: format!("Unable to find plugin {}", tmp[0]),
-                            }),
-                        });
-                    }
+            if let Some(children) = children {
+                if !collapsed {
+                    self.push_tree_items(tree, children, &child_prefix, false, items);
                 }
             }
+        }
+    }
 
-            pages.lock().await.push(Page {
-                widgets: pages_widgets,
-                focusable_widgets,
-            });
+    fn selected_pids(&self) -> Vec<sysinfo::Pid> {
+        if self.data.is_empty() {
+            return vec![];
+        }
+
+        let item = self.chart.select();
+        match self.mode {
+            ProcessListMode::Grouped => self
+                .sysinfo
+                .processes_by_exact_name(item.data.get("Name").unwrap_or(&item.name))
+                .map(|process| process.pid())
+                .collect(),
+            ProcessListMode::Tree | ProcessListMode::PerProcess => item
+                .data
+                .get("PID")
+                .and_then(|pid| pid.parse().ok())
+                .into_iter()
+                .collect(),
         }
     }
 
-    tokio::spawn(async move {
-        loop {
-            for page in &mut *pages_mutex.lock().await {
-                for el in &mut page.widgets {
-                    el.plugin.on_update();
+    /// Handle `key` if the signal picker is open, return false if it is closed
+    fn on_signal_prompt_input(&mut self, key: &str) -> bool {
+        self.signal_prompt = match std::mem::replace(&mut self.signal_prompt, SignalPrompt::Closed)
+        {
+            SignalPrompt::Closed => return false,
+            SignalPrompt::Picking { pids, selected } => match key {
+                "KEY_DOWN" | "k" => SignalPrompt::Picking {
+                    pids,
+                    selected: (selected + 1).min(SIGNALS.len() - 1),
+                },
+                "KEY_UP" | "j" => SignalPrompt::Picking {
+                    pids,
+                    selected: selected.saturating_sub(1),
+                },
+                "^J" | "KEY_ENTER" => SignalPrompt::Confirming {
+                    pids,
+                    signal: selected,
+                },
+                "^[" | "n" => SignalPrompt::Closed,
+                _ => SignalPrompt::Picking { pids, selected },
+            },
+            SignalPrompt::Confirming { pids, signal } => match key {
+                "^J" | "KEY_ENTER" | "y" => {
+                    self.send_signal(&pids, signal);
+                    SignalPrompt::Closed
                 }
+                "^[" | "n" => SignalPrompt::Closed,
+                _ => SignalPrompt::Confirming { pids, signal },
+            },
+        };
+        true
+    }
+
+    fn send_signal(&mut self, pids: &[sysinfo::Pid], signal: usize) {
+        let (signal_name, signal_number, _) = SIGNALS[signal];
+        let mut errors = vec![];
+        for pid in pids {
+            if unsafe { libc::kill(pid.as_u32() as libc::pid_t, signal_number) } != 0 {
+                errors.push(format!("{}: {}", pid, std::io::Error::last_os_error()));
             }
-            std::thread::sleep(std::time::Duration::from_millis(333));
         }
-    });
 
-    let mut widgets;
-    {
-        let locked_pages = pages.lock().await;
-        widgets = create_widget_window(
-            height - 2,
-            width,
-            locked_pages[current_page_number - 1].widgets.len() as i32,
+        self.report_status(&format!("{} sent to", signal_name), pids.len(), errors);
+    }
+
+    /// Add `delta` to the niceness of the selected processes
+    fn renice(&mut self, delta: i32) {
+        let pids = self.selected_pids();
+        if pids.is_empty() {
+            return;
+        }
+        let mut errors = vec![];
+        for pid in &pids {
+            let result = get_niceness(*pid).and_then(|niceness| {
+                let niceness = (niceness + delta).clamp(-20, 19);
+                if unsafe {
+                    libc::setpriority(libc::PRIO_PROCESS, pid.as_u32() as libc::id_t, niceness)
+                } == 0
+                {
+                    Ok(())
+                } else {
+                    Err(std::io::Error::last_os_error())
+                }
+            });
+            if let Err(error) = result {
+                errors.push(format!("{}: {}", pid, error));
+            }
+        }
+
+        self.report_status(
+            if delta > 0 {
+                "Niceness raised for"
+            } else {
+                "Niceness lowered for"
+            },
+            pids.len(),
+            errors,
         );
+        self.update_data();
     }
 
-    let current_os = sysinfo.name().unwrap_or_else(|| String::from("You"));
-    attron(ncurses::A_BOLD());
-    attron(COLOR_PAIR(4));
-    addstr(" rtop ");
-    attrset(ncurses::A_NORMAL());
-    addstr(&format!("for {}", current_os));
-    refresh();
-    display_help(height);
+    fn report_status(&mut self, action: &str, total: usize, errors: Vec<String>) {
+        let mut status = format!("{} {}/{} processes", action, total - errors.len(), total);
+        for error in errors {
+            status += &format!(" | {}", error);
+        }
+        self.status = Some(status);
+    }
+
+    fn signal_prompt_display(&self, h: i32) -> std::option::Option<String> {
+        let pids_list = |pids: &[sysinfo::Pid]| {
+            pids.iter()
+                .map(sysinfo::Pid::to_string)
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        match &self.signal_prompt {
+            SignalPrompt::Closed => None,
+            SignalPrompt::Picking { pids, selected } => {
+                let mut output = format!(
+                    "[[EFFECT_BOLD]]Send a signal to {} processes[[EFFECT_BOLD]] ({})\n\n",
+                    pids.len(),
+                    pids_list(pids)
+                );
+                for (i, (name, _, description)) in SIGNALS.iter().enumerate() {
+                    if i == *selected {
+                        output += &format!("[[EFFECT_REVERSE]] {:<8} {} [[EFFECT_REVERSE]]\n", name, description);
+                    } else {
+                        output += &format!(" {:<8} {}\n", name, description);
+                    }
+                }
+                output += &"\n".repeat((h - SIGNALS.len() as i32 - 3).max(0) as usize);
+                output += "Enter: Select  Esc: Cancel";
+                Some(output)
+            }
+            SignalPrompt::Confirming { pids, signal } => Some(format!(
+                "[[EFFECT_BOLD]]Send {} to {} processes?[[EFFECT_BOLD]]\n\nPIDs: {}\n\ny: Confirm  n: Cancel",
+                SIGNALS[*signal].0,
+                pids.len(),
+                pids_list(pids)
+            )),
+        }
+    }
+
+    /// Handle `key` if the search prompt is open, return false if it is closed
+    fn on_search_input(&mut self, key: &str) -> bool {
+        if !self.search_editing.is_capturing() {
+            return false;
+        }
+
+        let mut query = self
+            .search
+            .as_ref()
+            .map_or_else(String::new, |search| String::from(&search.query));
+        match key {
+            "^J" | "KEY_ENTER" => self.search_editing.set(false),
+            "^[" => {
+                self.search_editing.set(false);
+                query.clear();
+            }
+            "KEY_BACKSPACE" | "^?" | "^H" => {
+                query.pop();
+            }
+            _ if key.chars().count() == 1 => query += key,
+            _ => return true,
+        }
+
+        self.search = if query.is_empty() {
+            None
+        } else {
+            Some(ProcessSearch::new(&query))
+        };
+        self.update_data();
+        true
+    }
+
+    /// Select the next matching item if `forward` or the previous one, wrapping around the
+    /// list. The tree also lists the ancestors of the matching processes, they are skipped
+    fn jump_to_match(&mut self, forward: bool) {
+        let selected_item = |chart: &components::listview::ListView| {
+            let item = chart.select();
+            (String::from(&item.name), item.data.get("PID").cloned())
+        };
+
+        for _ in 0..self.data.len() {
+            let previous_item = selected_item(&self.chart);
+            if forward {
+                self.chart.next();
+            } else {
+                self.chart.previous();
+            }
+            if selected_item(&self.chart) == previous_item {
+                if forward {
+                    self.chart.to_first();
+                } else {
+                    self.chart.to_last();
+                }
+            }
+            if self.item_matches(self.chart.select()) {
+                return;
+            }
+        }
+    }
+
+    /// Whether the process of `item` matches the search, the listed groups always match
+    fn item_matches(&self, item: &components::listview::ListItem) -> bool {
+        item.data
+            .get("PID")
+            .and_then(|pid| pid.parse().ok())
+            .and_then(|pid: sysinfo::Pid| self.sysinfo.process(pid))
+            .is_none_or(|process| self.process_matches(process))
+    }
+
+    /// Color the part of the names of the `ListView` output matching the current search
+    fn highlight_search(&self, output: &str) -> String {
+        let search = match &self.search {
+            Some(search) => search,
+            None => return String::from(output),
+        };
+
+        let mut lines = output.lines();
+        let header = lines.next().unwrap_or_default();
+        let primary_key = self.primary_key();
+        let header_text = window::strip_markup(header);
+        let name_width = primary_key.chars().count()
+            + header_text
+                .get(primary_key.len()..)
+                .unwrap_or_default()
+                .chars()
+                .take_while(|c| *c == ' ')
+                .count();
+
+        let mut highlighted = format!("{}\n", header);
+        for line in lines {
+            let (prefix, row) = line
+                .strip_prefix("[[EFFECT_REVERSE]]")
+                .map_or(("", line), |row| ("[[EFFECT_REVERSE]]", row));
+            let name_end = row
+                .char_indices()
+                .nth(name_width)
+                .map_or(row.len(), |(i, _)| i);
+            let (name, columns) = row.split_at(name_end);
+            // The search matches the names without their branches in the tree
+            let (decoration, name) = if self.mode == ProcessListMode::Tree {
+                name.split_at(tree_decoration_len(name))
+            } else {
+                ("", name)
+            };
+
+            let found = search
+                .regex
+                .find(name.trim_end())
+                .filter(|found| !found.is_empty());
+            if let Some(found) = found {
+                highlighted += &format!(
+                    "{}{}{}[[EFFECT_COLOR_YELLOW]]{}[[EFFECT_COLOR_YELLOW]]{}{}\n",
+                    prefix,
+                    decoration,
+                    &name[..found.start()],
+                    found.as_str(),
+                    &name[found.end()..],
+                    columns
+                );
+            } else {
+                highlighted += &format!("{}\n", line);
+            }
+        }
+        highlighted
+    }
+
+    fn sort_by(&mut self, key: &str, ordering: Ordering) {
+        let key = if key == "Name" && self.mode == ProcessListMode::PerProcess {
+            "Command"
+        } else {
+            key
+        };
+        self.sort_key = String::from(key);
+        self.sort_inversed = ordering == Ordering::Inversed;
+
+        if self.mode == ProcessListMode::Tree {
+            self.update_data();
+        } else {
+            self.chart.sort_by(
+                std::option::Option::from(String::from(key)),
+                std::option::Option::from(ordering),
+            );
+        }
+    }
+
+    /// Switch to `mode`, or go back to the grouped mode if `mode` is already the current one
+    fn switch_mode(&mut self, mode: ProcessListMode) {
+        self.mode = if self.mode == mode {
+            ProcessListMode::Grouped
+        } else {
+            mode
+        };
+        self.sort_key = match (self.mode, self.sort_key.as_str()) {
+            (ProcessListMode::PerProcess, "Name") => String::from("Command"),
+            (ProcessListMode::Grouped | ProcessListMode::Tree, "Command") => String::from("Name"),
+            _ => String::from(&self.sort_key),
+        };
+        self.visible_columns = self.mode_columns().0;
+        self.chart = self.create_listview();
+        self.update_data();
+    }
+
+    fn create_listview(&self) -> components::listview::ListView {
+        let ordering = if self.sort_inversed {
+            Ordering::Inversed
+        } else {
+            Ordering::Default
+        };
+
+        match self.mode {
+            ProcessListMode::Grouped => components::listview::ListView::new(
+                0,
+                0,
+                &Vec::new(),
+                String::from("Name"),
+                self.visible_columns.clone(),
+                std::option::Option::from(String::from(&self.sort_key)),
+                std::option::Option::from(ordering),
+            ),
+            // The tree order is computed by the widget itself, sorting the items would break it
+            ProcessListMode::Tree => components::listview::ListView::new(
+                0,
+                0,
+                &Vec::new(),
+                String::from("Name"),
+                self.visible_columns.clone(),
+                None,
+                None,
+            ),
+            ProcessListMode::PerProcess => components::listview::ListView::new(
+                0,
+                0,
+                &Vec::new(),
+                String::from("Command"),
+                self.visible_columns.clone(),
+                std::option::Option::from(String::from(&self.sort_key)),
+                std::option::Option::from(ordering),
+            ),
+        }
+    }
+}
+
+fn get_niceness(pid: sysinfo::Pid) -> std::io::Result<i32> {
+    // getpriority can legitimately return -1, errno must be checked to detect errors
+    unsafe {
+        *libc::__errno_location() = 0;
+        let niceness = libc::getpriority(libc::PRIO_PROCESS, pid.as_u32() as libc::id_t);
+        let error = std::io::Error::last_os_error();
+        if niceness == -1 && error.raw_os_error() != Some(0) {
+            Err(error)
+        } else {
+            Ok(niceness)
+        }
+    }
+}
+
+/// Niceness of `pid`, or `?` if it cannot be read
+fn niceness_string(pid: sysinfo::Pid) -> String {
+    get_niceness(pid).map_or_else(|_| String::from("?"), |niceness| niceness.to_string())
+}
+
+fn compute_subtree_usage(
+    processes: &std::collections::HashMap<sysinfo::Pid, sysinfo::Process>,
+    children: &std::collections::HashMap<sysinfo::Pid, Vec<sysinfo::Pid>>,
+    usage: &mut std::collections::HashMap<sysinfo::Pid, ProcessUsage>,
+    pid: sysinfo::Pid,
+) -> ProcessUsage {
+    let process = &processes[&pid];
+    let mut total = ProcessUsage {
+        cpu: process.cpu_usage(),
+        memory: process.memory(),
+        count: 1,
+    };
+    if let Some(process_children) = children.get(&pid) {
+        for child in process_children {
+            let child_usage = compute_subtree_usage(processes, children, usage, *child);
+            total.cpu += child_usage.cpu;
+            total.memory += child_usage.memory;
+            total.count += child_usage.count;
+        }
+    }
+    usage.insert(pid, total);
+    total
+}
+
+/// Length of the branches and fold marker before the name of a process in the tree mode
+fn tree_decoration_len(name: &str) -> usize {
+    let branches = name.trim_start_matches(['│', '├', '└', '─', ' ']);
+    let unmarked = ["[+] ", "[-] "]
+        .iter()
+        .find_map(|marker| branches.strip_prefix(marker))
+        .unwrap_or(branches);
+    name.len() - unmarked.len()
+}
+
+/// Cut or pad `line` with spaces to make it `width` cells wide
+fn fit_line(line: &str, width: usize) -> String {
+    let line: String = line.chars().take(width).collect();
+    let padding = width.saturating_sub(line.chars().count());
+    format!("{}{}", line, " ".repeat(padding))
+}
+
+impl widget::Widget for ProcessList {
+    fn on_update(&mut self) {
+        let sysinfo = &mut self.sysinfo;
+        let refresh_progress = &mut self.refresh_progress;
+        let refresh_ticks = self.refresh_ticks;
+        let groups = self.source.sample(
+            |frame| &mut frame.processes,
+            || {
+                *refresh_progress += 1;
+                if *refresh_progress < refresh_ticks {
+                    return None;
+                }
+                *refresh_progress = 0;
+                sysinfo.refresh_processes();
+                Some(metrics::process_groups(sysinfo))
+            },
+        );
+        if let Some(groups) = groups {
+            self.groups = groups;
+            self.update_data();
+        }
+    }
+
+    fn display(&mut self, h: i32, w: i32) -> String {
+        if let Some(prompt) = self.signal_prompt_display(h) {
+            return prompt;
+        }
+
+        self.fit_columns(w as usize);
+        // The `ListView` cannot draw a header wider than the widget
+        if self.primary_key().len() + self.columns_width(&self.visible_columns) > w as usize {
+            return fit_line("Too narrow", w as usize);
+        }
+        let items = self.fitted_items(w as usize);
+        // The `ListView` overflows when emptied twice, a new one is only emptied once
+        if items.is_empty() {
+            self.chart = self.create_listview();
+        }
+        self.chart.resize(h, w);
+        self.chart.update_items(&items);
+
+        let output = self.chart.display();
+        let output = self.highlight_search(&output);
+        let footer = if self.search_editing.is_capturing() {
+            self.search
+                .as_ref()
+                .map_or_else(|| String::from("/"), |search| format!("/{}", search.query))
+                .into()
+        } else {
+            self.status.clone()
+        };
+        if let Some(status) = footer {
+            // The status replaces the last line, resizing the ListView would lose its scroll position
+            let rows = (h as usize).saturating_sub(1);
+            let mut lines: Vec<&str> = output.lines().take(rows).collect();
+            lines.resize(rows, "");
+            format!(
+                "{}\n{}",
+                lines.join("\n"),
+                status.chars().take(w as usize).collect::<String>()
+            )
+        } else {
+            output
+        }
+    }
+
+    fn on_input(&mut self, key: String) {
+        // An empty key means that no key was pressed before the input timeout
+        if key.is_empty() || self.on_signal_prompt_input(&key) || self.on_search_input(&key) {
+            return;
+        }
+        self.status = None;
+        if self.source.mode() == recording::Mode::Replay
+            && ["d", "+", "-", "t", "P"].contains(&key.as_str())
+        {
+            self.status = Some(String::from("Only the process groups are replayed"));
+            return;
+        }
+
+        if key == "KEY_DOWN" || key == "k" {
+            self.chart.next();
+        } else if key == "KEY_UP" || key == "j" {
+            self.chart.previous();
+        } else if key == "g" {
+            self.chart.to_first();
+        } else if key == "G" {
+            self.chart.to_last();
+        } else if key == "m" {
+            self.sort_by("Memory %", Ordering::Default);
+        } else if key == "c" {
+            self.sort_by("CPU %", Ordering::Default);
+        } else if key == "/" {
+            self.search_editing.set(true);
+        } else if key == "n" && self.search.is_some() {
+            self.jump_to_match(true);
+        } else if key == "N" && self.search.is_some() {
+            self.jump_to_match(false);
+        } else if key == "n" {
+            self.sort_by("Name", Ordering::Inversed);
+        } else if key == "C" {
+            self.sort_by("Count", Ordering::Default);
+        } else if key == "p" {
+            self.sort_by("PID", Ordering::Inversed);
+        } else if key == "u" {
+            self.sort_by("User", Ordering::Inversed);
+        } else if key == "s" {
+            self.sort_by("State", Ordering::Inversed);
+        } else if key == "S" {
+            self.sort_by("Start", Ordering::Default);
+        } else if key == "e" {
+            self.sort_by("Elapsed", Ordering::Default);
+        } else if key == "T" {
+            self.sort_by("Threads", Ordering::Default);
+        } else if key == "i" {
+            self.sort_by("Nice", Ordering::Inversed);
+        } else if key == "+" {
+            self.renice(1);
+        } else if key == "-" {
+            self.renice(-1);
+        } else if key == "t" {
+            self.switch_mode(ProcessListMode::Tree);
+        } else if key == "P" {
+            self.switch_mode(ProcessListMode::PerProcess);
+        } else if key == " " && self.mode == ProcessListMode::Tree {
+            if let Some(pid) = self.selected_pids().first() {
+                if !self.collapsed_processes.remove(pid) {
+                    self.collapsed_processes.insert(*pid);
+                }
+                self.update_data();
+            }
+        } else if key == "d" {
+            let pids = self.selected_pids();
+            if !pids.is_empty() {
+                self.signal_prompt = SignalPrompt::Picking { pids, selected: 0 };
+            }
+        }
+    }
+
+    fn title(&mut self) -> std::option::Option<String> {
+        match self.mode {
+            ProcessListMode::Grouped => Some(format!("[{}] Processes", self.data.len())),
+            ProcessListMode::Tree => Some(format!("[{}] Process Tree", self.data.len())),
+            ProcessListMode::PerProcess => {
+                Some(format!("[{}] Processes (per PID)", self.data.len()))
+            }
+        }
+    }
+}
+impl widget::Widget for CpuUsage {
+    fn on_update(&mut self) {
+        if self.source.charts_restarted(&mut self.restarts) {
+            self.data.clear();
+        }
+        let sysinfo = &mut self.sysinfo;
+        let usage = self.source.sample(
+            |frame| &mut frame.cpu,
+            || {
+                sysinfo.refresh_cpu();
+                Some(metrics::cpu_percent(sysinfo))
+            },
+        );
+        if let Some(usage) = usage {
+            self.data.push(usage as i32);
+        }
+    }
+
+    fn display(&mut self, h: i32, w: i32) -> String {
+        self.chart.resize(w, h);
+        self.visible_points = (w * 2) as usize;
+        let window = self.data.window(self.visible_points, self.zoom);
+        self.visible_peak = window.iter().map(|bucket| bucket.max).max();
+        colorize(
+            &self.chart.display(&chart_points(&window)),
+            self.color.as_deref(),
+        )
+    }
+
+    fn on_input(&mut self, key: String) {
+        self.zoom = zoom_chart(self.zoom, &key, &self.data, self.visible_points);
+    }
+
+    fn title(&mut self) -> std::option::Option<String> {
+        match self.visible_peak {
+            Some(peak) if self.zoom > 1 => {
+                Some(format!("CPU Usage ×{}, peak {}%", self.zoom, peak))
+            }
+            _ => Some(String::from("CPU Usage")),
+        }
+    }
+}
+impl CpuCores {
+    /// Minimal size of a cell of the grid, smaller widgets use compact bars
+    const MIN_CELL_HEIGHT: i32 = 4;
+    const MIN_CELL_WIDTH: i32 = 16;
+
+    fn core_label(frequency: u64, i: usize) -> String {
+        if frequency >= 1000 {
+            format!("CPU{} {:.2}GHz", i, frequency as f32 / 1000.)
+        } else {
+            format!("CPU{} {}MHz", i, frequency)
+        }
+    }
+
+    fn grid_display(&mut self, h: i32, w: i32, columns: i32) -> String {
+        let rows = (self.frequencies.len() as i32 + columns - 1) / columns;
+        let cell_height = h / rows;
+        let cell_width = (w - (columns - 1)) / columns;
+        self.chart.resize(cell_width, cell_height - 1);
+        self.visible_points = (cell_width * 2) as usize;
+
+        let mut cells = vec![];
+        for (i, frequency) in self.frequencies.iter().enumerate() {
+            let label = Self::core_label(*frequency, i);
+            let usage = format!("{}%", self.data[i].last().unwrap_or(0));
+            let mut cell = vec![format!(
+                "{}{}{}",
+                label,
+                " ".repeat((cell_width as usize).saturating_sub(label.len() + usage.len())),
+                usage
+            )];
+            let points = chart_points(&self.data[i].window(self.visible_points, self.zoom));
+            cell.extend(self.chart.display(&points).lines().map(String::from));
+            cell.resize(cell_height as usize, String::new());
+            cells.push(
+                cell.into_iter()
+                    .map(|line| fit_line(&line, cell_width as usize))
+                    .collect::<Vec<String>>(),
+            );
+        }
+
+        let mut output = String::new();
+        for row_cells in cells.chunks(columns as usize) {
+            for line in 0..cell_height as usize {
+                let row_line = row_cells
+                    .iter()
+                    .map(|cell| cell[line].as_str())
+                    .collect::<Vec<&str>>()
+                    .join(" ");
+                output += &format!("{}\n", row_line);
+            }
+        }
+        output
+    }
+
+    fn bars_display(&self, h: i32, w: i32) -> String {
+        let core_count = self.frequencies.len();
+        let columns = (core_count as i32 + h - 1) / h.max(1);
+        let column_width = (w - (columns - 1)) / columns.max(1);
+
+        let line_count = core_count.min(h.max(1) as usize);
+        let mut lines = vec![String::new(); line_count];
+        for (i, frequency) in self.frequencies.iter().enumerate() {
+            let usage = self.data[i].last().unwrap_or(0).clamp(0, 100);
+            let mut label = format!("{:<7}", Self::core_label(*frequency, i));
+            if column_width - (label.chars().count() as i32) - 6 < 1 {
+                label = format!("{:<6}", format!("CPU{}", i));
+            }
+            let bar_width = column_width - label.chars().count() as i32 - 6;
+            let color = if usage >= 90 {
+                components::bar::Color::Red
+            } else if usage >= 70 {
+                components::bar::Color::Yellow
+            } else {
+                components::bar::Color::Green
+            };
+
+            let line = &mut lines[i % line_count];
+            if !line.is_empty() {
+                *line += " ";
+            }
+            *line += &format!(
+                "{} {}{:>4}%",
+                label,
+                components::bar::Horizontal::new(1, bar_width.max(1), Some(color))
+                    .display(usage as f32)
+                    .trim_end_matches('\n'),
+                usage
+            );
+        }
+        lines.join("\n")
+    }
+}
+
+impl widget::Widget for CpuCores {
+    fn on_update(&mut self) {
+        if self.source.charts_restarted(&mut self.restarts) {
+            self.data.clear();
+        }
+        let sysinfo = &mut self.sysinfo;
+        let cores = self.source.sample(
+            |frame| &mut frame.cpu_cores,
+            || {
+                sysinfo.refresh_cpu();
+                let usages = metrics::cpu_cores_percent(sysinfo);
+                Some(
+                    usages
+                        .into_iter()
+                        .zip(sysinfo.cpus())
+                        .map(|(usage, cpu)| recording::CoreFrame {
+                            usage,
+                            frequency: cpu.frequency(),
+                        })
+                        .collect(),
+                )
+            },
+        );
+        let Some(cores) = cores else {
+            return;
+        };
+
+        let retention = self.retention;
+        self.data
+            .resize_with(cores.len(), || history::History::new(retention));
+        for (data, core) in self.data.iter_mut().zip(&cores) {
+            data.push(core.usage as i32);
+        }
+        self.frequencies = cores.iter().map(|core| core.frequency).collect();
+    }
+
+    fn display(&mut self, h: i32, w: i32) -> String {
+        let core_count = self.frequencies.len() as i32;
+        if core_count == 0 || self.data.len() < core_count as usize {
+            return String::new();
+        }
+
+        let grid_columns = (1..=core_count).find(|columns| {
+            let rows = (core_count + columns - 1) / columns;
+            h / rows >= Self::MIN_CELL_HEIGHT
+                && (w - (columns - 1)) / columns >= Self::MIN_CELL_WIDTH
+        });
+        match grid_columns {
+            Some(columns) => self.grid_display(h, w, columns),
+            None => self.bars_display(h, w),
+        }
+    }
+
+    fn on_input(&mut self, key: String) {
+        if let Some(data) = self.data.first() {
+            self.zoom = zoom_chart(self.zoom, &key, data, self.visible_points);
+        }
+    }
+
+    fn title(&mut self) -> std::option::Option<String> {
+        Some(zoomed_title(
+            &format!("CPU Cores ({})", self.frequencies.len()),
+            self.zoom,
+        ))
+    }
+}
+impl MemoryUsage {
+    /// Points of a chart `w` columns wide for `data`
+    fn points(&self, data: &history::History, w: i32) -> Vec<i32> {
+        chart_points(&data.window((w * 2) as usize, self.zoom))
+    }
+
+    fn measure(&mut self) -> recording::MemoryFrame {
+        self.sysinfo.refresh_memory();
+        let mut memory = recording::MemoryFrame {
+            total: self.sysinfo.total_memory(),
+            used: self.sysinfo.used_memory(),
+            available: self.sysinfo.available_memory(),
+            cached: None,
+            buffers: None,
+            swap_total: self.sysinfo.total_swap(),
+            swap_used: self.sysinfo.used_swap(),
+        };
+
+        // The page cache and buffers sizes are not exposed by sysinfo
+        if let Ok(meminfo) = std::fs::read_to_string("/proc/meminfo") {
+            for line in meminfo.lines() {
+                let mut parts = line.split_whitespace();
+                let field = match parts.next() {
+                    Some("Cached:") => &mut memory.cached,
+                    Some("Buffers:") => &mut memory.buffers,
+                    _ => continue,
+                };
+                *field = parts
+                    .next()
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .map(|value| value * 1024);
+            }
+        }
+        memory
+    }
+
+    /// Fields of the summary shown above the charts
+    fn header_fields(&self) -> Vec<String> {
+        let mut fields = vec![
+            format!(
+                "Used {}/{}",
+                format_bytes(self.memory.used),
+                format_bytes(self.memory.total)
+            ),
+            format!("Avail {}", format_bytes(self.memory.available)),
+        ];
+        if let Some(cached_memory) = self.memory.cached {
+            fields.push(format!("Cache {}", format_bytes(cached_memory)));
+        }
+        if let Some(buffers_memory) = self.memory.buffers {
+            fields.push(format!("Buf {}", format_bytes(buffers_memory)));
+        }
+        fields.push(format!(
+            "Swap {}/{}",
+            format_bytes(self.memory.swap_used),
+            format_bytes(self.memory.swap_total)
+        ));
+        fields
+    }
+}
+
+/// `fields` separated by two spaces, on as many lines of `width` cells as they need
+fn wrap_fields(fields: &[String], width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for field in fields {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 2 + field.chars().count() <= width => {
+                *line += &format!("  {}", field);
+            }
+            _ => lines.push(String::from(field)),
+        }
+    }
+    lines
+}
+
+/// Format a size in bytes with a binary unit, like `1.5G`
+fn format_bytes(bytes: u64) -> String {
+    let units = ["B", "K", "M", "G", "T", "P"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024. && unit < units.len() - 1 {
+        value /= 1024.;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{}{}", bytes, units[unit])
+    } else {
+        format!("{:.1}{}", value, units[unit])
+    }
+}
+
+/// Number of points kept by the charts of a widget, given by its `history` option
+fn chart_retention(options: &serde_json::Value) -> usize {
+    options
+        .get("history")
+        .and_then(serde_json::Value::as_u64)
+        .map_or(history::DEFAULT_RETENTION, |retention| retention as usize)
+}
+
+/// History of a chart widget, keeping the number of points given by its `history` option
+fn chart_history(options: &serde_json::Value) -> history::History {
+    history::History::new(chart_retention(options))
+}
+
+/// Title of a chart widget, with its zoom when zoomed out
+fn zoomed_title(title: &str, zoom: usize) -> String {
+    if zoom > 1 {
+        format!("{} ×{}", title, zoom)
+    } else {
+        String::from(title)
+    }
+}
+
+/// Zoom of a chart showing `points` points of `data` after pressing `key`, one of
+/// `ZOOM_KEYS`: `(` shows a time window twice as long, up to the whole history, `)` half as long
+fn zoom_chart(zoom: usize, key: &str, data: &history::History, points: usize) -> usize {
+    match key {
+        ")" => (zoom / 2).max(1),
+        "(" if zoom * points < data.len() => zoom * 2,
+        _ => zoom,
+    }
+}
+
+/// Keys zooming the charts of the current page, whichever widget is focused
+const ZOOM_KEYS: [&str; 2] = ["(", ")"];
+
+/// Whether the built-in widget `name` shows charts, which take the zoom keys
+fn is_chart_widget(name: &str) -> bool {
+    BUILTIN_OPTIONS
+        .iter()
+        .any(|(widget, options)| *widget == name && options.contains(&"history"))
+}
+
+/// Points of a chart for `window`, the average of each group of points
+fn chart_points(window: &[history::Bucket]) -> Vec<i32> {
+    window.iter().map(history::Bucket::average).collect()
+}
+
+/// Color name given in the options of a widget, as a `[[EFFECT_...]]` markup name. Without the
+/// option, the chart color of the theme
+fn chart_color(options: &serde_json::Value, key: &str) -> std::option::Option<String> {
+    let Some(color) = options.get(key) else {
+        return Some(String::from("COLOR_CHART"));
+    };
+    let color = color.as_str()?.to_lowercase();
+    CHART_COLORS
+        .contains(&color.as_str())
+        .then(|| format!("COLOR_{}", color.to_uppercase()))
+}
+
+/// Colors of the `color` options of the built-in widgets
+const CHART_COLORS: [&str; 8] = [
+    "red", "green", "yellow", "blue", "magenta", "cyan", "white", "black",
+];
+
+fn colorize(text: &str, color: std::option::Option<&str>) -> String {
+    if let Some(color) = color {
+        format!("[[EFFECT_{0}]]{1}[[EFFECT_{0}]]", color, text)
+    } else {
+        String::from(text)
+    }
+}
+
+/// Write `label` over the leading spaces of the first line of a chart
+fn label_chart(chart: &str, label: &str) -> String {
+    let (first_line, rest) = chart.split_once('\n').unwrap_or((chart, ""));
+    let free_space = first_line.chars().take_while(|c| *c == ' ').count();
+    if free_space <= label.chars().count() {
+        return String::from(chart);
+    }
+
+    format!(
+        "{}{}\n{}",
+        label,
+        first_line
+            .chars()
+            .skip(label.chars().count())
+            .collect::<String>(),
+        rest
+    )
+}
+
+impl NetworkHistory {
+    fn new(retention: usize) -> Self {
+        Self {
+            received: history::History::new(retention),
+            transmitted: history::History::new(retention),
+            total_received: 0,
+            total_transmitted: 0,
+        }
+    }
+}
+
+impl NetworkUsage {
+    const ALL_INTERFACES: &'static str = "All";
+
+    fn measure(&mut self) -> recording::NetworkFrame {
+        self.refresh_progress += 1;
+        if self.refresh_progress == 30 {
+            self.sysinfo.refresh_networks_list();
+            self.refresh_progress = 0;
+        } else {
+            self.sysinfo.refresh_networks();
+        }
+        let interval = self.last_update.elapsed().as_millis() as u64;
+        self.last_update = std::time::Instant::now();
+
+        let mut interfaces = std::collections::BTreeMap::new();
+        for (name, network) in self.sysinfo.networks() {
+            interfaces.insert(
+                String::from(name),
+                recording::Traffic {
+                    received: network.received(),
+                    transmitted: network.transmitted(),
+                },
+            );
+        }
+        recording::NetworkFrame {
+            interval,
+            interfaces,
+        }
+    }
+
+    fn selected_interface(&self) -> &str {
+        if self.selected == 0 {
+            Self::ALL_INTERFACES
+        } else {
+            self.history
+                .keys()
+                .filter(|name| *name != Self::ALL_INTERFACES)
+                .nth(self.selected - 1)
+                .map_or(Self::ALL_INTERFACES, String::as_str)
+        }
+    }
+}
+
+/// Display a chart of `data` in bytes per second with its label on top, scaled on the visible
+/// maximum. Each point of the chart is the average of `zoom` points of the history
+fn throughput_chart(
+    chart: &mut components::chart::Chart,
+    label: &str,
+    data: &history::History,
+    zoom: usize,
+    h: i32,
+    w: i32,
+) -> String {
+    let points = chart_points(&data.window((w * 2) as usize, zoom));
+    chart.higher_value = points.iter().copied().max().unwrap_or(0).max(1024);
+    chart.resize(w, h - 1);
+
+    let label = format!(
+        "{} {}/s",
+        label,
+        format_bytes(data.last().unwrap_or(0) as u64)
+    );
+    let mut output = format!(
+        "{}\n{}",
+        fit_line(&label, w as usize),
+        chart.display(&points)
+    );
+    let lines = output.lines().count();
+    output += &"\n".repeat((h as usize).saturating_sub(lines));
+    output
+}
+
+impl widget::Widget for NetworkUsage {
+    fn on_update(&mut self) {
+        if self.source.charts_restarted(&mut self.restarts) {
+            self.history.clear();
+        }
+        let source = self.source.clone();
+        let Some(networks) = source.sample(|frame| &mut frame.networks, || Some(self.measure()))
+        else {
+            return;
+        };
+        let elapsed = networks.interval.max(1) as f64 / 1000.;
+
+        let per_second = |bytes: u64| (bytes as f64 / elapsed).min(i32::MAX as f64) as i32;
+        let retention = self.retention;
+        let mut all = (0, 0);
+        for (name, traffic) in &networks.interfaces {
+            let history = self
+                .history
+                .entry(String::from(name))
+                .or_insert_with(|| NetworkHistory::new(retention));
+            history.received.push(per_second(traffic.received));
+            history.transmitted.push(per_second(traffic.transmitted));
+            history.total_received += traffic.received;
+            history.total_transmitted += traffic.transmitted;
+            all.0 += traffic.received;
+            all.1 += traffic.transmitted;
+        }
+
+        let history = self
+            .history
+            .entry(String::from(Self::ALL_INTERFACES))
+            .or_insert_with(|| NetworkHistory::new(retention));
+        history.received.push(per_second(all.0));
+        history.transmitted.push(per_second(all.1));
+        history.total_received += all.0;
+        history.total_transmitted += all.1;
+    }
+
+    fn display(&mut self, h: i32, w: i32) -> String {
+        let interface = String::from(self.selected_interface());
+        let Some(history) = self.history.get(&interface) else {
+            return String::new();
+        };
+        self.visible_points = (w * 2) as usize;
+
+        let received_height = (h + 1) / 2;
+        format!(
+            "{}{}",
+            throughput_chart(
+                &mut self.chart,
+                "RX",
+                &history.received,
+                self.zoom,
+                received_height,
+                w
+            ),
+            throughput_chart(
+                &mut self.chart,
+                "TX",
+                &history.transmitted,
+                self.zoom,
+                h - received_height,
+                w
+            )
+        )
+    }
+
+    fn on_input(&mut self, key: String) {
+        if key == "KEY_DOWN" || key == "k" {
+            self.selected = (self.selected + 1).min(self.history.len().saturating_sub(1));
+        } else if key == "KEY_UP" || key == "j" {
+            self.selected = self.selected.saturating_sub(1);
+        } else if let Some(history) = self.history.get(self.selected_interface()) {
+            self.zoom = zoom_chart(self.zoom, &key, &history.received, self.visible_points);
+        }
+    }
+
+    fn title(&mut self) -> std::option::Option<String> {
+        let interface = self.selected_interface();
+        let (total_received, total_transmitted) =
+            self.history.get(interface).map_or((0, 0), |history| {
+                (history.total_received, history.total_transmitted)
+            });
+
+        Some(format!(
+            "{}: {} [{}/{}] ↓ {} ↑ {}",
+            zoomed_title("Network", self.zoom),
+            interface,
+            self.selected + 1,
+            self.history.len().max(1),
+            format_bytes(total_received),
+            format_bytes(total_transmitted)
+        ))
+    }
+}
+impl widget::Widget for DiskUsage {
+    fn on_update(&mut self) {
+        let sysinfo = &mut self.sysinfo;
+        let refresh_progress = &mut self.refresh_progress;
+        let disks = self.source.sample(
+            |frame| &mut frame.disks,
+            || {
+                *refresh_progress += 1;
+                if *refresh_progress < 15 {
+                    return None;
+                }
+                *refresh_progress = 0;
+                sysinfo.refresh_disks_list();
+                Some(
+                    sysinfo
+                        .disks()
+                        .iter()
+                        .map(|disk| recording::DiskFrame {
+                            mount_point: disk.mount_point().to_string_lossy().into_owned(),
+                            total: disk.total_space(),
+                            available: disk.available_space(),
+                        })
+                        .collect(),
+                )
+            },
+        );
+        if let Some(disks) = disks {
+            self.disks = disks;
+        }
+    }
+
+    fn display(&mut self, h: i32, w: i32) -> String {
+        let mount_width = self
+            .disks
+            .iter()
+            .map(|disk| disk.mount_point.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(w as usize / 3);
+
+        let mut lines = vec![];
+        for disk in self.disks.iter().take(h as usize) {
+            let total = disk.total;
+            let used = total - disk.available.min(total);
+            let percent = (used * 100).checked_div(total).unwrap_or(0);
+            let details = format!(
+                " {:>3}% {}/{} free {}",
+                percent,
+                format_bytes(used),
+                format_bytes(total),
+                format_bytes(disk.available)
+            );
+
+            let bar_width = w - mount_width as i32 - details.chars().count() as i32 - 1;
+            let bar = if bar_width >= 5 {
+                let color = if percent >= 90 {
+                    components::bar::Color::Red
+                } else if percent >= 75 {
+                    components::bar::Color::Yellow
+                } else {
+                    components::bar::Color::Green
+                };
+                let bar = components::bar::Horizontal::new(1, bar_width, Some(color))
+                    .display(percent as f32);
+                format!(" {}", bar.trim_end_matches('\n'))
+            } else {
+                String::new()
+            };
+
+            lines.push(format!(
+                "{}{}{}",
+                fit_line(&disk.mount_point, mount_width),
+                bar,
+                details
+            ));
+        }
+        lines.join("\n")
+    }
+
+    fn title(&mut self) -> std::option::Option<String> {
+        Some(format!("[{}] Disks", self.disks.len()))
+    }
+}
+impl DiskIo {
+    /// Disk usage of the processes, every third update
+    fn measure(&mut self) -> std::option::Option<recording::DiskIoFrame> {
+        self.refresh_progress += 1;
+        if self.refresh_progress < 3 {
+            return None;
+        }
+        self.refresh_progress = 0;
+
+        self.sysinfo
+            .refresh_processes_specifics(sysinfo::ProcessRefreshKind::new().with_disk_usage());
+        let elapsed = self.last_update.elapsed().as_secs_f64().max(0.001);
+        self.last_update = std::time::Instant::now();
+        let per_second = |bytes: u64| (bytes as f64 / elapsed) as u64;
+
+        let mut total = (0, 0);
+        let mut processes = vec![];
+        for (pid, process) in self.sysinfo.processes() {
+            let disk_usage = process.disk_usage();
+            total.0 += disk_usage.read_bytes;
+            total.1 += disk_usage.written_bytes;
+            if disk_usage.read_bytes + disk_usage.written_bytes > 0 {
+                processes.push(recording::ProcessIoFrame {
+                    pid: pid.as_u32(),
+                    name: String::from(process.name()),
+                    read: per_second(disk_usage.read_bytes),
+                    written: per_second(disk_usage.written_bytes),
+                });
+            }
+        }
+        processes.sort_by_key(|process| std::cmp::Reverse(process.read + process.written));
+
+        Some(recording::DiskIoFrame {
+            read: per_second(total.0),
+            written: per_second(total.1),
+            processes,
+        })
+    }
+}
+impl widget::Widget for DiskIo {
+    fn on_update(&mut self) {
+        if self.source.charts_restarted(&mut self.restarts) {
+            self.read.clear();
+            self.written.clear();
+        }
+        let source = self.source.clone();
+        let Some(disk_io) = source.sample(|frame| &mut frame.disk_io, || self.measure()) else {
+            return;
+        };
+        self.read.push(disk_io.read.min(i32::MAX as u64) as i32);
+        self.written
+            .push(disk_io.written.min(i32::MAX as u64) as i32);
+        self.top_processes = disk_io.processes;
+    }
+
+    fn display(&mut self, h: i32, w: i32) -> String {
+        // Keep at least 4 lines for each chart, the rest is used to list the processes
+        let process_lines = ((h - 8).min(h / 3)).max(0) as usize;
+        let charts_height = h - process_lines as i32;
+        let read_height = (charts_height + 1) / 2;
+        self.visible_points = (w * 2) as usize;
+
+        let mut output = format!(
+            "{}{}",
+            throughput_chart(
+                &mut self.chart,
+                "Read",
+                &self.read,
+                self.zoom,
+                read_height,
+                w
+            ),
+            throughput_chart(
+                &mut self.chart,
+                "Write",
+                &self.written,
+                self.zoom,
+                charts_height - read_height,
+                w
+            )
+        );
+        if process_lines > 1 {
+            output += &fit_line(
+                &format!(
+                    "{:>7} {:<15} {:>9} {:>9}",
+                    "PID", "Name", "Read/s", "Write/s"
+                ),
+                w as usize,
+            );
+            for process in self.top_processes.iter().take(process_lines - 1) {
+                output += &format!(
+                    "\n{}",
+                    fit_line(
+                        &format!(
+                            "{:>7} {:<15} {:>9} {:>9}",
+                            process.pid,
+                            process.name.chars().take(15).collect::<String>(),
+                            format_bytes(process.read),
+                            format_bytes(process.written)
+                        ),
+                        w as usize
+                    )
+                );
+            }
+        }
+        output
+    }
+
+    fn on_input(&mut self, key: String) {
+        self.zoom = zoom_chart(self.zoom, &key, &self.read, self.visible_points);
+    }
+
+    fn title(&mut self) -> std::option::Option<String> {
+        Some(zoomed_title("Disk I/O", self.zoom))
+    }
+}
+impl Temperatures {
+    /// Temperature considered as critical when the sensor does not report one
+    const DEFAULT_CRITICAL: f32 = 100.;
+
+    /// Key of the history of the `index`th sensor: its label, and how many sensors before it
+    /// share that label, several sensors often having the same one
+    fn sensor_key(sensors: &[recording::SensorFrame], index: usize) -> (String, usize) {
+        let label = &sensors[index].label;
+        let occurrence = sensors[..index]
+            .iter()
+            .filter(|sensor| sensor.label == *label)
+            .count();
+        (label.clone(), occurrence)
+    }
+
+    fn sensor_line(sensor: &recording::SensorFrame, label_width: usize, w: i32) -> String {
+        let critical = sensor.critical.map_or_else(
+            || String::from("-"),
+            |critical| format!("{:.1}°C", critical),
+        );
+        fit_line(
+            &format!(
+                "{} {:>6.1}°C  max {:>6.1}°C  crit {}",
+                fit_line(&sensor.label, label_width),
+                sensor.temperature,
+                sensor.max,
+                critical
+            ),
+            w as usize,
+        )
+    }
+}
+
+impl widget::Widget for Temperatures {
+    fn on_update(&mut self) {
+        if self.source.charts_restarted(&mut self.restarts) {
+            self.data.clear();
+        }
+        let sysinfo = &mut self.sysinfo;
+        let sensors = self.source.sample(
+            |frame| &mut frame.temperatures,
+            || {
+                sysinfo.refresh_components();
+                Some(
+                    sysinfo
+                        .components()
+                        .iter()
+                        .map(|component| recording::SensorFrame {
+                            label: String::from(component.label()),
+                            temperature: component.temperature(),
+                            max: component.max(),
+                            critical: component.critical(),
+                        })
+                        .collect(),
+                )
+            },
+        );
+        let Some(sensors) = sensors else {
+            return;
+        };
+
+        let retention = self.retention;
+        let keys: Vec<(String, usize)> = (0..sensors.len())
+            .map(|i| Self::sensor_key(&sensors, i))
+            .collect();
+        self.data.retain(|key, _| keys.contains(key));
+        for (key, sensor) in keys.into_iter().zip(&sensors) {
+            self.data
+                .entry(key)
+                .or_insert_with(|| history::History::new(retention))
+                .push(sensor.temperature as i32);
+        }
+        self.sensors = sensors;
+        self.selected = self.selected.min(self.sensors.len().saturating_sub(1));
+    }
+
+    fn display(&mut self, h: i32, w: i32) -> String {
+        let sensors = &self.sensors;
+        if sensors.is_empty() {
+            return String::from("No temperature sensor found");
+        }
+
+        // The chart of the selected sensor is only shown if there is enough space left
+        let chart_height = if h - sensors.len() as i32 >= 5 {
+            h - sensors.len() as i32
+        } else if h >= 10 {
+            h / 2
+        } else {
+            0
+        };
+        let list_height = (h - chart_height) as usize;
+        let label_width = sensors
+            .iter()
+            .map(|sensor| sensor.label.chars().count())
+            .max()
+            .unwrap_or(0)
+            .min(w as usize / 2);
+
+        let first_line = (self.selected + 1).saturating_sub(list_height);
+        let mut lines = vec![];
+        for (i, sensor) in sensors
+            .iter()
+            .enumerate()
+            .skip(first_line)
+            .take(list_height)
+        {
+            let critical = sensor.critical.unwrap_or(Self::DEFAULT_CRITICAL);
+            let color = if sensor.temperature >= critical * 0.9 {
+                "[[EFFECT_COLOR_RED]]"
+            } else if sensor.temperature >= critical * 0.75 {
+                "[[EFFECT_COLOR_YELLOW]]"
+            } else {
+                "[[EFFECT_COLOR_GREEN]]"
+            };
+            let line = format!(
+                "{}{}{}",
+                color,
+                Self::sensor_line(sensor, label_width, w),
+                color
+            );
+
+            if i == self.selected {
+                lines.push(format!("[[EFFECT_REVERSE]]{}[[EFFECT_REVERSE]]", line));
+            } else {
+                lines.push(line);
+            }
+        }
+
+        let data = self
+            .data
+            .get(&Self::sensor_key(sensors, self.selected))
+            .filter(|_| chart_height > 0);
+        if let Some(data) = data {
+            let sensor = &sensors[self.selected];
+            self.chart.higher_value = sensor
+                .critical
+                .unwrap_or(Self::DEFAULT_CRITICAL)
+                .max(sensor.max) as i32;
+            self.chart.resize(w, chart_height);
+            self.visible_points = (w * 2) as usize;
+            let points = chart_points(&data.window(self.visible_points, self.zoom));
+            lines.resize(list_height, String::new());
+            lines.push(label_chart(&self.chart.display(&points), &sensor.label));
+        }
+        lines.join("\n")
+    }
+
+    fn on_input(&mut self, key: String) {
+        if key == "KEY_DOWN" || key == "k" {
+            self.selected = (self.selected + 1).min(self.sensors.len().saturating_sub(1));
+        } else if key == "KEY_UP" || key == "j" {
+            self.selected = self.selected.saturating_sub(1);
+        } else if let Some(data) = (self.selected < self.sensors.len())
+            .then(|| Self::sensor_key(&self.sensors, self.selected))
+            .and_then(|key| self.data.get(&key))
+        {
+            self.zoom = zoom_chart(self.zoom, &key, data, self.visible_points);
+        }
+    }
+
+    fn title(&mut self) -> std::option::Option<String> {
+        Some(zoomed_title(
+            &format!("[{}] Temperatures", self.sensors.len()),
+            self.zoom,
+        ))
+    }
+}
+impl widget::Widget for MemoryUsage {
+    fn display(&mut self, h: i32, w: i32) -> String {
+        // The summary leaves a line to the charts at least
+        let mut header_lines = wrap_fields(&self.header_fields(), w as usize);
+        header_lines.truncate((h - 1).max(1) as usize);
+        let header = header_lines
+            .iter()
+            .map(|line| fit_line(line, w as usize))
+            .collect::<Vec<String>>()
+            .join("\n");
+        let charts_height = h - header_lines.len() as i32;
+        self.visible_points = (w * 2) as usize;
+
+        // Each chart needs a line for its current value and a few lines for the graph
+        if self.memory.swap_total == 0 || charts_height < 6 {
+            self.chart.resize(w, charts_height);
+            return format!(
+                "{}\n{}",
+                header,
+                colorize(
+                    &label_chart(&self.chart.display(&self.points(&self.data, w)), "RAM"),
+                    self.color.as_deref()
+                )
+            );
+        }
+
+        let memory_height = (charts_height + 1) / 2;
+        self.chart.resize(w, memory_height);
+        self.swap_chart.resize(w, charts_height - memory_height);
+
+        let mut memory_chart = self.chart.display(&self.points(&self.data, w));
+        let chart_lines = memory_chart.lines().count();
+        memory_chart += &"\n".repeat((memory_height as usize).saturating_sub(chart_lines));
+        format!(
+            "{}\n{}{}",
+            header,
+            colorize(&label_chart(&memory_chart, "RAM"), self.color.as_deref()),
+            colorize(
+                &label_chart(
+                    &self.swap_chart.display(&self.points(&self.swap_data, w)),
+                    "Swap"
+                ),
+                self.swap_color.as_deref()
+            )
+        )
+    }
+
+    fn on_update(&mut self) {
+        if self.source.charts_restarted(&mut self.restarts) {
+            self.data.clear();
+            self.swap_data.clear();
+        }
+        let source = self.source.clone();
+        let Some(memory) = source.sample(|frame| &mut frame.memory, || Some(self.measure())) else {
+            return;
+        };
+        self.data
+            .push(metrics::percent(memory.used, memory.total) as i32);
+        self.swap_data
+            .push(metrics::percent(memory.swap_used, memory.swap_total) as i32);
+        self.memory = memory;
+    }
+
+    fn on_input(&mut self, key: String) {
+        self.zoom = zoom_chart(self.zoom, &key, &self.data, self.visible_points);
+    }
+
+    fn title(&mut self) -> std::option::Option<String> {
+        Some(zoomed_title("Memory", self.zoom))
+    }
+}
+impl widget::Widget for PluginError {
+    fn display(&mut self, h: i32, w: i32) -> String {
+        // Centered, and cut in windows narrower than the message
+        let error_message = format!("An error occured: {}", self.message);
+        let padding = ((w / 2).max(0) as usize).saturating_sub(error_message.chars().count() / 2);
+        format!(
+            "{}{}",
+            String::from("\n").repeat((h / 2).max(0) as usize),
+            fit_line(
+                &format!("{}{}", " ".repeat(padding), error_message),
+                w.max(0) as usize
+            )
+        )
+    }
+}
+
+struct ScreenWidget {
+    plugin: Box<dyn widget::Widget>,
+    name: String,
+    /// Set while the widget takes every key as text, never for plugins
+    capture: TextCapture,
+    /// Entry of the config which created the widget, `None` for errors
+    entry: std::option::Option<config::WidgetEntry>,
+    focusable: bool,
+}
+struct Page {
+    widgets: Vec<ScreenWidget>,
+    focusable_widgets: Vec<usize>,
+    layout: layout::Layout,
+    name: std::option::Option<String>,
+}
+unsafe impl Send for Page {}
+
+#[tokio::main]
+async fn main() {
+    let default_panic = std::panic::take_hook(); // Handle error, work only with builtin-plugin
+    std::panic::set_hook(Box::new(move |info| {
+        // TODO: Make this work for plugin
+        curs_set(ncurses::CURSOR_VISIBILITY::CURSOR_VISIBLE);
+        window::restore_palette();
+        endwin();
+        default_panic(info);
+        std::process::exit(1);
+    }));
+
+    let args = match cli::Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(error) => {
+            eprintln!("rtop: {}\nTry `rtop --help` for more information.", error);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+    if args.version {
+        println!("rtop {}", env!("CARGO_PKG_VERSION"));
+        return;
+    }
+    let refresh_interval = std::time::Duration::from_millis(args.refresh_ms.unwrap_or(333));
+    // Shared by the exporter and the export, which then measure the system once
+    let samples = (args.listen.is_some() || args.export.is_some())
+        .then(|| metrics::start_sampling(refresh_interval));
+    if let (Some(address), Some(samples)) = (args.listen, &samples) {
+        let listener = match tokio::net::TcpListener::bind(address).await {
+            Ok(listener) => listener,
+            Err(error) => {
+                eprintln!("rtop: unable to listen on {}: {}", address, error);
+                std::process::exit(1);
+            }
+        };
+        tokio::spawn(exporter::serve(listener, samples.clone()));
+        if args.headless {
+            // Serve until killed
+            std::future::pending::<()>().await;
+        }
+    }
+    if let (Some(format), Some(samples)) = (args.export, samples) {
+        match export_metrics(format, args.output.as_deref(), args.count, samples).await {
+            // The reader of the output is gone, such as `head`
+            Err(error) if error.kind() != std::io::ErrorKind::BrokenPipe => {
+                eprintln!("rtop: unable to export the metrics: {}", error);
+                std::process::exit(1);
+            }
+            _ => return,
+        }
+    }
+
+    let builtin_addon = builtin_widgets();
+    let explicit_config_path = args.config.as_ref().map(std::path::PathBuf::from);
+    // Without `--config`, the config is looked up again on each reload, such as on SIGHUP, as a
+    // file may have been created in a directory of higher priority
+    let find_config_path = || {
+        explicit_config_path
+            .clone()
+            .unwrap_or_else(config::find_config)
+    };
+    let mut config_path = find_config_path();
+    if explicit_config_path.is_some() && !config_path.is_file() {
+        eprintln!("rtop: no config file at {}", config_path.display());
+        std::process::exit(1);
+    }
+    let (mut option, mut plugins) = match load_config(&config_path, &builtin_addon) {
+        Ok(loaded) => loaded,
+        Err(problems) => {
+            eprintln!("Invalid config {}:", config_path.display());
+            for problem in problems {
+                eprintln!("  {}", problem.replace('\n', "\n  "));
+            }
+            std::process::exit(1);
+        }
+    };
+
+    if args.list_widgets {
+        list_widgets(&builtin_addon, &option, &plugins);
+        return;
+    }
+    if let Some(page) = args.page {
+        if page > option.pages.len() {
+            eprintln!(
+                "rtop: cannot start on page {}, the config has {} page(s)",
+                page,
+                option.pages.len()
+            );
+            std::process::exit(1);
+        }
+    }
+    if args.check_config {
+        if config_path.is_file() {
+            println!("{} is valid", config_path.display());
+        } else {
+            println!(
+                "No config file found, the default config is used. Create one at {}",
+                config_path.display()
+            );
+        }
+        return;
+    }
+    if args.snapshot {
+        let mut pages = build_pages(
+            &option,
+            &builtin_addon,
+            &plugins,
+            &recording::SampleSource::default(),
+            &mut vec![],
+        );
+        let theme_attrs = apply_theme(&option.theme(&config_path).unwrap_or_default(), true);
+        for tick in 0..args.ticks.unwrap_or(2) {
+            if tick > 0 {
+                tokio::time::sleep(refresh_interval).await;
+            }
+            update_widgets(&mut pages);
+        }
+
+        let (width, height) = args.size.unwrap_or((80, 24));
+        let page_count = pages.len();
+        let mut separator = "";
+        for (i, page) in pages.iter_mut().enumerate() {
+            if args.page.is_some_and(|page| page != i + 1) {
+                continue;
+            }
+            print!("{}", separator);
+            separator = "\n";
+            if let Some(name) = &page.name {
+                println!("Page {}/{}: {}", i + 1, page_count, name);
+            } else {
+                println!("Page {}/{}", i + 1, page_count);
+            }
+            let screen = snapshot_page(page, height, width, theme_attrs.window);
+            let lines = if args.ansi {
+                screen.ansi_lines()
+            } else {
+                screen.lines()
+            };
+            println!("{}", lines.join("\n"));
+        }
+        return;
+    }
+
+    // Shared by the built-in widgets of every page
+    let source = recording::SampleSource::default();
+    let replay = match &args.replay {
+        Some(path) => match recording::Replay::load(path) {
+            Ok(replay) => {
+                source.set_mode(recording::Mode::Replay);
+                Some(std::sync::Arc::new(tokio::sync::Mutex::new(replay)))
+            }
+            Err(error) => {
+                eprintln!("rtop: unable to replay {}: {}", path, error);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let mut recorder = match &args.record {
+        Some(path) => match recording::Recorder::open(path) {
+            Ok(recorder) => {
+                source.set_mode(recording::Mode::Recording);
+                Some(recorder)
+            }
+            Err(error) => {
+                eprintln!("rtop: unable to record to {}: {}", path, error);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let recording_error: std::sync::Arc<std::sync::Mutex<std::option::Option<String>>> =
+        std::sync::Arc::default();
+
+    let mut current_page_number = args.page.unwrap_or(1);
+    let sysinfo = sysinfo::System::new_all();
+    let mut current_widget = 1;
+
+    let locale = setlocale(LcCategory::all, "");
+    if !locale.contains("UTF-8") {
+        println!("You need to have a terminal that support UTF-8");
+        return;
+    }
+
+    //Initialize Ncurses
+    let term = initscr();
+    keypad(term, true);
+    start_color();
+    use_default_colors();
+    let mut height = 0;
+    let mut width = 0;
+    getmaxyx(term, &mut height, &mut width);
+    curs_set(ncurses::CURSOR_VISIBILITY::CURSOR_INVISIBLE);
+
+    timeout(334);
+    noecho();
+
+    // The theme was checked with the config
+    let mut theme_attrs = apply_theme(&option.theme(&config_path).unwrap_or_default(), false);
+
+    let pages: std::sync::Arc<tokio::sync::Mutex<std::vec::Vec<Page>>> =
+        std::sync::Arc::new(tokio::sync::Mutex::new(vec![]));
+    let pages_mutex = std::sync::Arc::clone(&pages);
+
+    *pages.lock().await = build_pages(&option, &builtin_addon, &plugins, &source, &mut vec![]);
+    // Libraries replaced by a config reload, kept loaded as their widgets may still be in use
+    let mut retired_plugins = vec![];
+    let mut config_modified = config_modified_time(&config_path);
+    let mut reload_status: std::option::Option<String> = None;
+    // SIGHUP asks to reload the config, the config is only reloaded on save if it cannot be caught
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()).ok();
+
+    let update_replay = replay.clone();
+    let update_source = source.clone();
+    let update_recording_error = std::sync::Arc::clone(&recording_error);
+    tokio::spawn(async move {
+        let load_sysinfo = sysinfo::System::new();
+        let mut last_update = std::time::Instant::now();
+        loop {
+            if let Some(replay) = &update_replay {
+                let mut replay = replay.lock().await;
+                // Seeking waits for the lock, so the charts never start again within a batch
+                if replay.take_seek() {
+                    update_source.restart_charts();
+                }
+                let frames = replay.advance(last_update.elapsed());
+                last_update = std::time::Instant::now();
+                let pages = &mut *pages_mutex.lock().await;
+                for frame in frames {
+                    update_source.begin_frame(frame);
+                    update_widgets(pages);
+                }
+            } else if let Some(writer) = &mut recorder {
+                let load_average = load_sysinfo.load_average();
+                update_source.begin_frame(recording::Frame {
+                    time: chrono::Local::now().timestamp_millis(),
+                    load_average: Some([load_average.one, load_average.five, load_average.fifteen]),
+                    ..recording::Frame::default()
+                });
+                update_widgets(&mut pages_mutex.lock().await);
+                let written = update_source.take_frame().map(|frame| writer.write(&frame));
+                if let Some(Err(error)) = written {
+                    *update_recording_error
+                        .lock()
+                        .unwrap_or_else(std::sync::PoisonError::into_inner) =
+                        Some(format!(" Recording stopped: {} ", error));
+                    update_source.set_mode(recording::Mode::Live);
+                    recorder = None;
+                }
+            } else {
+                update_widgets(&mut pages_mutex.lock().await);
+            }
+            tokio::time::sleep(refresh_interval).await;
+        }
+    });
+
+    let mut widgets;
+    {
+        let locked_pages = pages.lock().await;
+        widgets = create_widget_window(
+            height - 2,
+            width,
+            &locked_pages[current_page_number - 1].layout,
+            theme_attrs.window,
+        );
+    }
+
+    let current_os = sysinfo.name().unwrap_or_else(|| String::from("You"));
+    display_header(&current_os, theme_attrs.header);
+    refresh();
+    display_help(height, theme_attrs.header, replay.is_some());
 
     loop {
-        let current_page_widget_count;
+        if config_modified_time(&config_path) != config_modified
+            || hangup.as_mut().is_some_and(|hangup| {
+                hangup
+                    .poll_recv(&mut std::task::Context::from_waker(std::task::Waker::noop()))
+                    .is_ready()
+            })
+        {
+            config_path = find_config_path();
+            config_modified = config_modified_time(&config_path);
+            let locked_pages = &mut *pages.lock().await;
+            match reload_config(
+                &config_path,
+                &builtin_addon,
+                locked_pages,
+                &mut option,
+                &mut plugins,
+                &mut retired_plugins,
+                &source,
+            ) {
+                Ok(()) => {
+                    reload_status = None;
+                    theme_attrs =
+                        apply_theme(&option.theme(&config_path).unwrap_or_default(), false);
+                    current_page_number = current_page_number.min(locked_pages.len());
+                    current_widget = 1;
+                    erase();
+                    display_header(&current_os, theme_attrs.header);
+                    display_help(height, theme_attrs.header, replay.is_some());
+                    widgets = create_widget_window(
+                        height - 2,
+                        width,
+                        &locked_pages[current_page_number - 1].layout,
+                        theme_attrs.window,
+                    );
+                }
+                Err(problems) => {
+                    reload_status = Some(format!(
+                        " Config not reloaded: {} problem(s), run rtop --config {} --check-config ",
+                        problems.len(),
+                        config_path.display()
+                    ));
+                }
+            }
+        }
+
         let current_page_focusable_widget_count;
         {
             let locked_pages = &mut pages.lock().await;
             let current_page = &mut locked_pages[current_page_number - 1];
-            current_page_widget_count = current_page.widgets.len();
             current_page_focusable_widget_count = current_page.focusable_widgets.len();
 
             if current_page_focusable_widget_count == 0 {
                 current_widget = 0;
             }
 
-            for (i, item) in widgets
-                .iter_mut()
-                .enumerate()
-                .take(current_page_widget_count)
-            {
-                let widget = &mut current_page.widgets[i];
+            for (item, widget) in widgets.iter_mut().zip(&mut current_page.widgets) {
+                let Some(item) = item else {
+                    continue;
+                };
                 item.write(&widget.plugin.display(item.height - 2, item.width - 4));
                 let title = &widget.plugin.title();
                 if let Some(title) = title {
@@ -426,36 +2548,84 @@ async fn main() {
                     item.set_title(String::from(&widget.name));
                 }
 
-                item.set_border_color(COLOR_PAIR(2));
+                item.set_border_color(theme_attrs.window.border);
             }
             if current_page_focusable_widget_count > 1 {
                 let tmp = current_page.focusable_widgets[current_widget - 1] as usize;
-                widgets[(tmp) - 1].set_border_color(COLOR_PAIR(4));
-                widgets[(tmp) - 1].refresh();
+                if let Some(widget) = &mut widgets[(tmp) - 1] {
+                    widget.set_border_color(theme_attrs.focused_border);
+                    widget.refresh();
+                }
             }
-            for widget in widgets.iter().take(current_page_widget_count) {
+            for widget in widgets.iter().flatten() {
                 widget.refresh();
             }
+            if widgets.iter().all(std::option::Option::is_none) {
+                let message = "Terminal too small";
+                mvaddstr(
+                    height / 2,
+                    (width - message.len() as i32).max(0) / 2,
+                    message,
+                );
+            }
         }
 
         // Update TopBar and BottomBar Infos
-        let now = chrono::Local::now();
+        let mut now = chrono::Local::now();
         let load_average = sysinfo.load_average();
+        let mut load_average = [load_average.one, load_average.five, load_average.fifteen];
+        let mut replay_status = None;
+        if let Some(replay) = &replay {
+            let replay = replay.lock().await;
+            if let Some(frame) = replay.current_frame() {
+                now = chrono::TimeZone::timestamp_millis_opt(&chrono::Local, frame.time)
+                    .single()
+                    .unwrap_or(now);
+                load_average = frame.load_average.unwrap_or_default();
+            }
+            replay_status = Some(replay_status_line(&replay, now));
+        }
         let load_average_string = format!(
             " Load Average: {:.2} {:.2} {:.2} ",
-            load_average.one, load_average.five, load_average.fifteen
+            load_average[0], load_average[1], load_average[2]
         );
         mvaddstr(
             0,
             width - 9,
             &format!("{:02}:{:02}:{:02}", now.hour(), now.minute(), now.second()),
         );
-        let page_indicator = format!("[{}/{}]", current_page_number, pages.lock().await.len());
+        let page_indicator;
+        {
+            let locked_pages = pages.lock().await;
+            page_indicator = if let Some(name) = &locked_pages[current_page_number - 1].name {
+                format!("[{} {}/{}]", name, current_page_number, locked_pages.len())
+            } else {
+                format!("[{}/{}]", current_page_number, locked_pages.len())
+            };
+        }
         mvaddstr(
             height - 1,
             width - 1 - page_indicator.len() as i32,
             &page_indicator,
         );
+        let recording_error = recording_error
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .clone();
+        let status = reload_status
+            .clone()
+            .or(recording_error)
+            .map(|status| (status, theme_attrs.error))
+            .or_else(|| replay_status.map(|status| (status, theme_attrs.header)));
+        if let Some((status, status_attr)) = &status {
+            attron(*status_attr);
+            mvaddstr(
+                height - 1,
+                (width - 1 - page_indicator.len() as i32 - status.chars().count() as i32).max(0),
+                status,
+            );
+            attroff(*status_attr);
+        }
         mvaddstr(
             0,
             width / 2 - (load_average_string.len() / 2) as i32,
@@ -463,6 +2633,18 @@ async fn main() {
         );
 
         let key = getch();
+        // While the focused widget takes every key as text, the keys of the interface, including
+        // those of the replay and the zoom, are typed into it too. A resize is still handled
+        if key != ncurses::KEY_RESIZE {
+            let locked_pages = &mut pages.lock().await;
+            if send_captured_key(
+                &mut locked_pages[current_page_number - 1],
+                current_widget,
+                &ncurses::keyname(key).unwrap_or_default(),
+            ) {
+                continue;
+            }
+        }
         match key {
             ncurses::KEY_RIGHT => {
                 let locked_pages = pages.lock().await;
@@ -471,232 +2653,1502 @@ async fn main() {
                     current_page_number = locked_pages.len();
                 }
 
-                widgets = create_widget_window(
-                    height - 2,
-                    width,
-                    locked_pages[current_page_number - 1].widgets.len() as i32,
-                );
-                current_widget = 1;
-            }
-            ncurses::KEY_LEFT => {
-                let locked_pages = pages.lock().await;
-                current_page_number -= 1;
-                if current_page_number < 1 {
-                    current_page_number = 1;
-                }
-                widgets = create_widget_window(
-                    height - 2,
-                    width,
-                    locked_pages[current_page_number - 1].widgets.len() as i32,
+                widgets = create_widget_window(
+                    height - 2,
+                    width,
+                    &locked_pages[current_page_number - 1].layout,
+                    theme_attrs.window,
+                );
+                current_widget = 1;
+            }
+            ncurses::KEY_LEFT => {
+                let locked_pages = pages.lock().await;
+                current_page_number -= 1;
+                if current_page_number < 1 {
+                    current_page_number = 1;
+                }
+                widgets = create_widget_window(
+                    height - 2,
+                    width,
+                    &locked_pages[current_page_number - 1].layout,
+                    theme_attrs.window,
+                );
+                current_widget = 1;
+            }
+            ncurses::KEY_RESIZE => {
+                erase();
+                refresh();
+                getmaxyx(term, &mut height, &mut width);
+                resizeterm(0, 0);
+                display_header(&current_os, theme_attrs.header);
+                display_help(height, theme_attrs.header, replay.is_some());
+                let locked_pages = pages.lock().await;
+                widgets = create_widget_window(
+                    height - 2,
+                    width,
+                    &locked_pages[current_page_number - 1].layout,
+                    theme_attrs.window,
+                );
+            }
+            9 => {
+                // TAB Key
+                current_widget += 1;
+                if current_widget > current_page_focusable_widget_count {
+                    current_widget = current_page_focusable_widget_count;
+                }
+            }
+            353 => {
+                // BackTAB Key
+                current_widget -= 1;
+                if current_widget < 1 {
+                    current_widget = 1;
+                }
+            }
+            90 => {
+                // BackTAB Key
+                current_widget -= 1;
+                if current_widget < 1 {
+                    current_widget = 1;
+                }
+            }
+            113 => exit(),
+            _ => {
+                let key_name = ncurses::keyname(key).unwrap_or_default();
+                match (&replay, key_name.as_str()) {
+                    (Some(replay), " ") => replay.lock().await.toggle_pause(),
+                    (Some(replay), "[" | "]") => {
+                        let mut replay = replay.lock().await;
+                        let speed = if key_name == "[" {
+                            replay.speed() / 2.
+                        } else {
+                            replay.speed() * 2.
+                        };
+                        replay.set_speed(speed);
+                    }
+                    (Some(replay), "<" | ">" | "{" | "}") => {
+                        let offset = match key_name.as_str() {
+                            "<" => -10_000,
+                            ">" => 10_000,
+                            "{" => -60_000,
+                            _ => 60_000,
+                        };
+                        // The charts start again at the next update, fed with the frames before
+                        // the new position
+                        replay.lock().await.seek(offset);
+                    }
+                    (_, key) if ZOOM_KEYS.contains(&key) => {
+                        let locked_pages = &mut pages.lock().await;
+                        for widget in &mut locked_pages[current_page_number - 1].widgets {
+                            if is_chart_widget(&widget.name) {
+                                widget.plugin.on_input(key_name.clone());
+                            }
+                        }
+                    }
+                    _ if current_widget != 0 => {
+                        let locked_pages = &mut pages.lock().await;
+                        let current_page = &mut locked_pages[current_page_number - 1];
+                        current_page.widgets
+                            [(current_page.focusable_widgets[current_widget - 1] as usize) - 1]
+                            .plugin
+                            .on_input(key_name);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Send `key` to the focused widget of `page` if it takes every key as text and return true,
+/// `current_widget` being the focused widget counting from 1, or 0 for none
+fn send_captured_key(page: &mut Page, current_widget: usize, key: &str) -> bool {
+    let focused = current_widget
+        .checked_sub(1)
+        .and_then(|i| page.focusable_widgets.get(i));
+    let Some(widget) = focused.map(|widget| &mut page.widgets[widget - 1]) else {
+        return false;
+    };
+    if !widget.capture.is_capturing() {
+        return false;
+    }
+    widget.plugin.on_input(String::from(key));
+    true
+}
+
+fn update_widgets(pages: &mut [Page]) {
+    for page in pages {
+        for widget in &mut page.widgets {
+            widget.plugin.on_update();
+        }
+    }
+}
+
+/// Options understood by each built-in widget, the others are reported by `load_config`
+const BUILTIN_OPTIONS: [(&str, &[&str]); 8] = [
+    ("cpu_chart", &["color", "history"]),
+    ("cpu_cores", &["history"]),
+    ("disk_io", &["history"]),
+    ("disk_usage", &[]),
+    ("memory_chart", &["color", "swap_color", "history"]),
+    ("network_chart", &["history"]),
+    (
+        "process_list",
+        &["mode", "sort", "sort_inversed", "columns", "refresh_ticks"],
+    ),
+    ("temperatures", &["history"]),
+];
+
+/// Modes of the `mode` option of the process list
+const PROCESS_LIST_MODES: [&str; 3] = ["grouped", "tree", "per_process"];
+
+/// `names` quoted and separated by commas
+fn quoted_list(names: &[&str]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Problems with the value of `option` in `options`, a known option of a built-in widget, one
+/// per invalid value
+fn builtin_option_problems(options: &serde_json::Value, option: &str) -> Vec<String> {
+    let value = &options[option];
+    let invalid = |expected: String| vec![format!("invalid value {} ({})", value, expected)];
+    match option {
+        "color" | "swap_color" => {
+            if value
+                .as_str()
+                .is_some_and(|color| CHART_COLORS.contains(&color.to_lowercase().as_str()))
+            {
+                vec![]
+            } else {
+                invalid(format!("expected one of {}", quoted_list(&CHART_COLORS)))
+            }
+        }
+        "history" | "refresh_ticks" if value.as_u64().is_none_or(|count| count == 0) => {
+            invalid(String::from("expected a positive number of updates"))
+        }
+        "mode"
+            if !value
+                .as_str()
+                .is_some_and(|mode| PROCESS_LIST_MODES.contains(&mode)) =>
+        {
+            invalid(format!(
+                "expected one of {}",
+                quoted_list(&PROCESS_LIST_MODES)
+            ))
+        }
+        "sort_inversed" if !value.is_boolean() => {
+            invalid(String::from("expected `true` or `false`"))
+        }
+        "columns" => match value.as_array() {
+            None => invalid(String::from("expected a list of columns")),
+            Some(columns) => columns
+                .iter()
+                .filter(|column| {
+                    !column
+                        .as_str()
+                        .is_some_and(|column| PROCESS_COLUMNS.contains(&column))
+                })
+                .map(|column| {
+                    format!(
+                        "invalid column {} (expected one of {})",
+                        column,
+                        quoted_list(&PROCESS_COLUMNS)
+                    )
+                })
+                .collect(),
+        },
+        "sort" => {
+            // The per-process columns can only sort the list if they are shown
+            let mut columns: Vec<&str> = PROCESS_GROUP_COLUMNS.to_vec();
+            columns.push("Command");
+            match options["columns"].as_array() {
+                Some(given_columns) => columns.extend(
+                    given_columns
+                        .iter()
+                        .filter_map(serde_json::Value::as_str)
+                        .filter(|column| PROCESS_COLUMNS.contains(column)),
+                ),
+                None => columns.extend(PROCESS_COLUMNS),
+            }
+            let mut shown_columns = vec![];
+            for column in columns {
+                if !shown_columns.contains(&column) {
+                    shown_columns.push(column);
+                }
+            }
+            if value
+                .as_str()
+                .is_some_and(|sort_key| shown_columns.contains(&sort_key))
+            {
+                vec![]
+            } else {
+                invalid(format!(
+                    "expected a shown column, one of {}",
+                    quoted_list(&shown_columns)
+                ))
+            }
+        }
+        _ => vec![],
+    }
+}
+
+fn builtin_widgets() -> std::collections::HashMap<String, WidgetInitializer> {
+    let mut builtin_addon: std::collections::HashMap<String, WidgetInitializer> =
+        std::collections::HashMap::new();
+    builtin_addon.insert(String::from("memory_chart"), init_memory_plugin);
+    builtin_addon.insert(String::from("cpu_chart"), init_cpuusage_plugin);
+    builtin_addon.insert(String::from("cpu_cores"), init_cpucores_plugin);
+    builtin_addon.insert(String::from("process_list"), init_process_plugin);
+    builtin_addon.insert(String::from("network_chart"), init_network_plugin);
+    builtin_addon.insert(String::from("disk_usage"), init_disk_usage_plugin);
+    builtin_addon.insert(String::from("disk_io"), init_disk_io_plugin);
+    builtin_addon.insert(String::from("temperatures"), init_temperatures_plugin);
+    builtin_addon
+}
+
+/// Read the config at `path` and load its plugins, listing every problem found on the way
+fn load_config(
+    path: &std::path::Path,
+    builtin_addon: &std::collections::HashMap<String, WidgetInitializer>,
+) -> Result<
+    (
+        config::Config,
+        std::collections::HashMap<String, libloading::Library>,
+    ),
+    Vec<String>,
+> {
+    let option = match std::fs::read_to_string(path) {
+        Ok(content) => config::Config::parse(&content, config::Format::from_path(path)),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            config::Config::parse("{}", config::Format::Json)
+        }
+        Err(error) => return Err(vec![format!("unable to read the file: {}", error)]),
+    }
+    .map_err(|error| vec![error])?;
+    let mut problems = option.check();
+    if let Err(problem) = option.theme(path) {
+        problems.push(problem);
+    }
+
+    let mut plugins = std::collections::HashMap::new();
+    let mut unloaded_plugins = std::collections::HashSet::new();
+    for plugin in &option.plugins {
+        // Duplicated names are already reported by `Config::check`
+        if plugin.path.is_empty()
+            || plugins.contains_key(&plugin.name)
+            || unloaded_plugins.contains(plugin.name.as_str())
+        {
+            continue;
+        }
+        match unsafe { libloading::Library::new(&plugin.path) } {
+            Ok(library) => {
+                plugins.insert(String::from(&plugin.name), library);
+            }
+            Err(error) => {
+                problems.push(format!(
+                    "plugin `{}`: unable to load {}: {}",
+                    plugin.name, plugin.path, error
+                ));
+                unloaded_plugins.insert(plugin.name.as_str());
+            }
+        }
+    }
+
+    for (i, page) in option.pages.iter().enumerate() {
+        for widget in page.widgets() {
+            if builtin_addon.contains_key(&widget.name) {
+                let known_options = BUILTIN_OPTIONS
+                    .iter()
+                    .find(|(name, _)| *name == widget.name)
+                    .map_or(&[][..], |(_, options)| *options);
+                // Options which are not an object are already reported by `Config::check`
+                for option in widget
+                    .options
+                    .as_object()
+                    .into_iter()
+                    .flat_map(|o| o.keys())
+                {
+                    if known_options.contains(&option.as_str()) {
+                        for problem in builtin_option_problems(&widget.options, option) {
+                            problems.push(format!(
+                                "page {}: option `{}` of widget `{}`: {}",
+                                i + 1,
+                                option,
+                                widget.name,
+                                problem
+                            ));
+                        }
+                        continue;
+                    }
+                    problems.push(if known_options.is_empty() {
+                        format!(
+                            "page {}: widget `{}` takes no option, found `{}`",
+                            i + 1,
+                            widget.name,
+                            option
+                        )
+                    } else {
+                        format!(
+                            "page {}: unknown option `{}` for widget `{}`, its options are {}",
+                            i + 1,
+                            option,
+                            widget.name,
+                            quoted_list(known_options)
+                        )
+                    });
+                }
+                continue;
+            }
+
+            let problem = match widget.name.split_once('.') {
+                None => {
+                    let mut builtin_names: Vec<&String> = builtin_addon.keys().collect();
+                    builtin_names.sort();
+                    format!(
+                        "unknown widget `{}`, built-in widgets are {}",
+                        widget.name,
+                        builtin_names
+                            .iter()
+                            .map(|name| format!("`{}`", name))
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    )
+                }
+                Some((plugin_name, _)) if unloaded_plugins.contains(plugin_name) => continue,
+                Some((plugin_name, plugin_widget)) => {
+                    if let Some(plugin) = plugins.get(plugin_name) {
+                        let initializer_v2: PluginSymbolResult<PluginInitializerV2> =
+                            unsafe { plugin.get(format!("init_v2_{}", plugin_widget).as_bytes()) };
+                        let initializer_v1: PluginSymbolResult<PluginInitializerV1> =
+                            unsafe { plugin.get(format!("init_{}", plugin_widget).as_bytes()) };
+                        if initializer_v2.is_ok() || initializer_v1.is_ok() {
+                            continue;
+                        }
+                        format!(
+                            "plugin `{}` has no widget `{}`, it exports neither `init_{}` nor `init_v2_{}`",
+                            plugin_name, plugin_widget, plugin_widget, plugin_widget
+                        )
+                    } else {
+                        format!(
+                            "unknown plugin `{}` for widget `{}`",
+                            plugin_name, widget.name
+                        )
+                    }
+                }
+            };
+            problems.push(format!("page {}: {}", i + 1, problem));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok((option, plugins))
+    } else {
+        Err(problems)
+    }
+}
+
+fn list_widgets(
+    builtin_addon: &std::collections::HashMap<String, WidgetInitializer>,
+    option: &config::Config,
+    plugins: &std::collections::HashMap<String, libloading::Library>,
+) {
+    let mut builtin_names: Vec<&String> = builtin_addon.keys().collect();
+    builtin_names.sort();
+    println!("Built-in widgets:");
+    for name in builtin_names {
+        println!("  {}", name);
+    }
+
+    for plugin in &option.plugins {
+        if let Some(library) = plugins.get(&plugin.name) {
+            println!("Widgets of plugin {} ({}):", plugin.name, plugin.path);
+            for widget in plugin_widgets(&plugin.path, library) {
+                println!("  {}.{}", plugin.name, widget);
+            }
+        }
+    }
+}
+
+/// Widgets exported by a plugin, found by looking for the names of their initializers in the
+/// library file and checking that the library really exports them
+fn plugin_widgets(path: &str, library: &libloading::Library) -> Vec<String> {
+    let content = std::fs::read(path).unwrap_or_default();
+    let mut widgets: Vec<String> = content
+        .split(|byte| *byte == 0)
+        .filter_map(|symbol| std::str::from_utf8(symbol).ok())
+        .filter_map(|symbol| {
+            symbol
+                .strip_prefix("init_v2_")
+                .or_else(|| symbol.strip_prefix("init_"))
+                .filter(|widget| {
+                    !widget.is_empty()
+                        && widget
+                            .chars()
+                            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+                })
+                .filter(|_| {
+                    unsafe { library.get::<PluginInitializerV1>(symbol.as_bytes()) }.is_ok()
+                })
+        })
+        .map(String::from)
+        .collect();
+    widgets.sort();
+    widgets.dedup();
+    widgets
+}
+
+/// Create the pages of `option`. Widgets of `previous_widgets` created by the same entry are
+/// moved to the new pages instead of being created again, so they keep their state
+fn build_pages(
+    option: &config::Config,
+    builtin_addon: &std::collections::HashMap<String, WidgetInitializer>,
+    plugins: &std::collections::HashMap<String, libloading::Library>,
+    source: &recording::SampleSource,
+    previous_widgets: &mut Vec<ScreenWidget>,
+) -> Vec<Page> {
+    let mut pages = vec![];
+    for page in &option.pages {
+        let entries = page.widgets();
+        if entries.is_empty() {
+            pages.push(Page {
+                widgets: vec![ScreenWidget {
+                    name: String::from("Error"),
+                    plugin: Box::new(PluginError {
+                        message: String::from("You must add a widget to this page"),
+                    }),
+                    entry: None,
+                    focusable: false,
+                    capture: TextCapture::default(),
+                }],
+                focusable_widgets: vec![],
+                layout: layout::Layout::auto(1),
+                name: page.name(),
+            });
+            continue;
+        }
+
+        let mut pages_widgets = vec![];
+        let mut focusable_widgets = vec![];
+        for (i, entry) in entries.into_iter().enumerate() {
+            let widget = previous_widgets
+                .iter()
+                .position(|widget| widget.entry.as_ref() == Some(&entry))
+                .map_or_else(
+                    || create_widget(entry, builtin_addon, plugins, source),
+                    |position| previous_widgets.remove(position),
                 );
-                current_widget = 1;
-            }
-            ncurses::KEY_RESIZE => {
-                erase();
-                refresh();
-                getmaxyx(term, &mut height, &mut width);
-                resizeterm(0, 0);
-                attron(ncurses::A_BOLD());
-                attron(COLOR_PAIR(4));
-                addstr(" rtop ");
-                attrset(ncurses::A_NORMAL());
-                addstr(&format!("for {}", current_os));
-                display_help(height);
-                widgets = create_widget_window(height - 2, width, current_page_widget_count as i32);
-            }
-            9 => {
-                // TAB Key
-                current_widget += 1;
-                if current_widget > current_page_focusable_widget_count {
-                    current_widget = current_page_focusable_widget_count;
-                }
+            if widget.focusable {
+                focusable_widgets.push(i + 1);
             }
-            353 => {
-                // BackTAB Key
-                current_widget -= 1;
-                if current_widget < 1 {
-                    current_widget = 1;
-                }
+            pages_widgets.push(widget);
+        }
+
+        pages.push(Page {
+            widgets: pages_widgets,
+            focusable_widgets,
+            layout: page.layout(),
+            name: page.name(),
+        });
+    }
+    pages
+}
+
+fn create_widget(
+    entry: config::WidgetEntry,
+    builtin_addon: &std::collections::HashMap<String, WidgetInitializer>,
+    plugins: &std::collections::HashMap<String, libloading::Library>,
+    source: &recording::SampleSource,
+) -> ScreenWidget {
+    let capture = TextCapture::default();
+    let created_widget = if let Some(initializer) = builtin_addon.get(&entry.name) {
+        Ok(initializer(&entry.options, source, &capture))
+    } else {
+        let (plugin_name, plugin_widget) = entry.name.split_once('.').unwrap_or((&entry.name, ""));
+        if let Some(plugin) = plugins.get(plugin_name) {
+            let initializer_v2: PluginSymbolResult<PluginInitializerV2> =
+                unsafe { plugin.get(format!("init_v2_{}", plugin_widget).as_bytes()) };
+            let initializer_v1: PluginSymbolResult<PluginInitializerV1> =
+                unsafe { plugin.get(format!("init_{}", plugin_widget).as_bytes()) };
+            match (initializer_v2, initializer_v1) {
+                (Ok(initializer), _) => Ok(initializer(&entry.options.to_string())),
+                (Err(_), Ok(initializer)) => Ok(initializer()),
+                (Err(_), Err(_)) => Err(format!(
+                    "Unknow widget {} in plugin {}",
+                    plugin_widget, plugin_name
+                )),
             }
-            90 => {
-                // BackTAB Key
-                current_widget -= 1;
-                if current_widget < 1 {
-                    current_widget = 1;
-                }
+        } else {
+            Err(format!("Unable to find plugin {}", plugin_name))
+        }
+    };
+
+    match created_widget {
+        Ok((mut plugin, focusable)) => {
+            plugin.init();
+            ScreenWidget {
+                plugin,
+                name: String::from(&entry.name),
+                entry: Some(entry),
+                focusable,
+                capture,
             }
-            113 => exit(),
-            _ => {
-                if current_widget != 0 {
-                    let locked_pages = &mut pages.lock().await;
-                    let current_page = &mut locked_pages[current_page_number - 1];
-                    current_page.widgets
-                        [(current_page.focusable_widgets[current_widget - 1] as usize) - 1]
-                        .plugin
-                        .on_input(ncurses::keyname(key).unwrap_or_default());
-                }
+        }
+        Err(message) => ScreenWidget {
+            plugin: Box::new(PluginError { message }),
+            name: String::from("Error"),
+            entry: None,
+            focusable: false,
+            capture,
+        },
+    }
+}
+
+fn config_modified_time(path: &std::path::Path) -> std::option::Option<std::time::SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Replace `pages` by the pages of the config at `path`, keeping the widgets whose entry did not
+/// change. Nothing is changed if the new config has problems
+fn reload_config(
+    path: &std::path::Path,
+    builtin_addon: &std::collections::HashMap<String, WidgetInitializer>,
+    pages: &mut Vec<Page>,
+    option: &mut config::Config,
+    plugins: &mut std::collections::HashMap<String, libloading::Library>,
+    retired_plugins: &mut Vec<libloading::Library>,
+    source: &recording::SampleSource,
+) -> Result<(), Vec<String>> {
+    let (new_option, new_plugins) = load_config(path, builtin_addon)?;
+
+    let plugin_path = |option: &config::Config, name: &str| {
+        option
+            .plugins
+            .iter()
+            .find(|plugin| plugin.name == name)
+            .map(|plugin| String::from(&plugin.path))
+    };
+    let mut previous_widgets: Vec<ScreenWidget> =
+        pages.drain(..).flat_map(|page| page.widgets).collect();
+    // Widgets of a plugin now loaded from another library must be created again
+    previous_widgets.retain(|widget| {
+        match widget
+            .entry
+            .as_ref()
+            .and_then(|entry| entry.name.split_once('.'))
+        {
+            Some((plugin_name, _)) => {
+                plugin_path(option, plugin_name) == plugin_path(&new_option, plugin_name)
             }
+            None => true,
+        }
+    });
+
+    *pages = build_pages(
+        &new_option,
+        builtin_addon,
+        &new_plugins,
+        source,
+        &mut previous_widgets,
+    );
+    // Dropped widgets must be gone before their library can be retired
+    drop(previous_widgets);
+    retired_plugins.extend(std::mem::replace(plugins, new_plugins).into_values());
+    *option = new_option;
+    Ok(())
+}
+
+/// Write each new sample of `samples` to the file at `output`, or to stdout, `count` times or
+/// until stopped
+async fn export_metrics(
+    format: cli::ExportFormat,
+    output: std::option::Option<&str>,
+    count: std::option::Option<u64>,
+    mut samples: metrics::Samples,
+) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?,
+        ),
+        None => Box::new(std::io::stdout()),
+    };
+    let mut exported = 0;
+    while count.is_none_or(|count| exported < count) {
+        if samples.changed().await.is_err() {
+            // The sampling stopped
+            break;
         }
+        let Some(sample) = samples.borrow_and_update().clone() else {
+            continue;
+        };
+        let json = match format {
+            cli::ExportFormat::Json => serde_json::to_string_pretty(&sample),
+            cli::ExportFormat::Ndjson => serde_json::to_string(&sample),
+        }?;
+        writeln!(writer, "{}", json)?;
+        writer.flush()?;
+        exported += 1;
+    }
+    Ok(())
+}
+
+/// Attributes of the parts of the screen styled by the theme
+#[derive(Clone, Copy)]
+struct ThemeAttrs {
+    window: window::WindowStyle,
+    focused_border: attr_t,
+    header: attr_t,
+    error: attr_t,
+}
+
+/// Create the colors of `theme`, or of the monochrome theme if the terminal has no colors.
+/// `headless` is for drawing into buffers, without ncurses
+fn apply_theme(theme: &theme::Theme, headless: bool) -> ThemeAttrs {
+    let theme = if headless || has_colors() {
+        *theme
+    } else {
+        theme::Theme::builtin("monochrome").unwrap_or_default()
+    };
+
+    if headless {
+        window::init_headless_colors(theme.widget_colors);
+    } else {
+        window::init_colors(theme.widget_colors);
+    }
+    window::define_color("CHART", window::style_attr(theme.chart));
+    ThemeAttrs {
+        window: window::WindowStyle {
+            border: window::style_attr(theme.border),
+            title: window::style_attr(theme.title),
+            text: window::style_attr(theme.text),
+            border_style: theme.border_style,
+        },
+        focused_border: window::style_attr(theme.focused_border),
+        header: window::style_attr(theme.header),
+        error: window::style_attr(theme.error),
     }
 }
 
-fn display_help(win_height: i32) {
+fn display_header(current_os: &str, header_attr: attr_t) {
+    attron(header_attr);
+    addstr(" rtop ");
+    attrset(ncurses::A_NORMAL());
+    addstr(&format!("for {}", current_os));
+}
+
+/// Position, speed and state of a replay for the bottom bar, `now` being the time of the last
+/// replayed frame. Always as wide, as it is written over the previous one
+fn replay_status_line(replay: &recording::Replay, now: chrono::DateTime<chrono::Local>) -> String {
+    let duration = (replay.end() - replay.start()).max(1);
+    let state = if replay.is_paused() {
+        "paused"
+    } else if replay.is_finished() {
+        "ended"
+    } else {
+        "playing"
+    };
+    format!(
+        " Replay {} {:>3}% x{:<4} {:<7} ",
+        now.format("%Y-%m-%d %H:%M:%S"),
+        (replay.position() - replay.start()) * 100 / duration,
+        replay.speed(),
+        state
+    )
+}
+
+/// Keys of the help bar, those of a replay when `replaying`
+fn display_help(win_height: i32, header_attr: attr_t, replaying: bool) {
     let mut help: std::collections::HashMap<&str, &str> = std::collections::HashMap::new();
     help.insert("Q", "Quit");
     help.insert("J", "Down");
     help.insert("K", "Up");
     help.insert("g", "Jump to top");
     help.insert("G", "Jump to bottom");
-    help.insert("dd", "Kill process");
+    help.insert("d", "Send signal");
+    help.insert("+/-", "Renice");
+    help.insert("(/)", "Zoom");
+    help.insert("/", "Search");
     help.insert("m", "Sort by memory");
-    help.insert("n", "Sort by name");
+    help.insert("n", "Sort by name, next match when searching");
+    help.insert("N", "Previous match");
     help.insert("c", "Sort by CPU");
     help.insert("C", "Sort by count");
+    help.insert("t", "Tree view");
+    help.insert("P", "Per PID view");
+    help.insert("Space", "Fold subtree");
+    if replaying {
+        for key in ["d", "+/-", "t", "P"] {
+            help.remove(key);
+        }
+        help.insert("Space", "Pause");
+        help.insert("</>", "Seek 10s");
+        help.insert("{/}", "Seek 1min");
+        help.insert("[/]", "Speed");
+    }
 
     mv(win_height - 1, 0);
 
     for (key, value) in help {
-        attron(ncurses::A_BOLD());
-        attron(COLOR_PAIR(4));
+        attron(header_attr);
         addstr(&format!(" {} ", key));
-        attroff(ncurses::A_BOLD());
-        attroff(COLOR_PAIR(4));
+        attroff(header_attr);
         addstr(&format!("{} ", value));
     }
 }
 
 fn exit() {
     curs_set(ncurses::CURSOR_VISIBILITY::CURSOR_VISIBLE);
+    window::restore_palette();
     endwin();
     std::process::exit(0);
 }
 
-fn create_widget_window(height: i32, width: i32, widget_count: i32) -> Vec<window::Window> {
-    let mut win_height = height;
-    let mut win_width = width;
-    if widget_count >= 2 {
-        win_height = height / 2;
-    }
-    if widget_count == 4 {
-        win_width = width / 2;
-    }
+fn create_widget_window(
+    height: i32,
+    width: i32,
+    page_layout: &layout::Layout,
+    style: window::WindowStyle,
+) -> Vec<std::option::Option<window::Window>> {
+    page_layout
+        .compute(layout::Rect {
+            x: 0,
+            y: 1,
+            width,
+            height,
+        })
+        .iter()
+        .enumerate()
+        .map(|(i, area)| {
+            // Windows too small to hold a widget are not drawn at all
+            area.can_display_widget().then(|| {
+                window::Window::new(
+                    area.height,
+                    area.width,
+                    area.x,
+                    area.y,
+                    style,
+                    (i + 1).to_string(),
+                )
+            })
+        })
+        .collect()
+}
 
-    let widget1 = window::Window::new(
-        win_height,
-        win_width,
-        0,
-        1,
-        COLOR_PAIR(2),
-        COLOR_PAIR(4),
-        String::from("1"),
-    );
-    let widget2 = if widget_count == 3 {
-        window::Window::new(
-            height - win_height,
-            (width as f32 / 2.).ceil() as i32,
-            0,
-            1 + win_height,
-            COLOR_PAIR(2),
-            COLOR_PAIR(4),
-            String::from("2"),
-        )
-    } else if widget_count == 4 {
-        window::Window::new(
-            win_height,
-            width - win_width,
-            win_width,
-            1,
-            COLOR_PAIR(2),
-            COLOR_PAIR(4),
-            String::from("2"),
-        )
-    } else {
-        window::Window::new(
-            height - win_height,
-            win_width,
-            0,
-            1 + win_height,
-            COLOR_PAIR(2),
-            COLOR_PAIR(4),
-            String::from("2"),
-        )
-    };
-    let widget3 = if widget_count == 4 {
-        window::Window::new(
-            height - win_height,
-            win_width,
-            0,
-            1 + win_height,
-            COLOR_PAIR(2),
-            COLOR_PAIR(4),
-            String::from("3"),
-        )
-    } else {
-        window::Window::new(
-            height - win_height,
-            width - (width as f32 / 2.).ceil() as i32,
-            width - (width / 2),
-            1 + win_height,
-            COLOR_PAIR(2),
-            COLOR_PAIR(4),
-            String::from("3"),
-        )
-    };
-    let widget4 = window::Window::new(
-        height - win_height,
-        width - win_width,
-        win_width,
-        1 + win_height,
-        COLOR_PAIR(2),
-        COLOR_PAIR(4),
-        String::from("4"),
-    );
+/// Draw the widgets of `page` like on the screen, into a buffer of `height` rows and `width`
+/// columns
+fn snapshot_page(
+    page: &mut Page,
+    height: i32,
+    width: i32,
+    style: window::WindowStyle,
+) -> window::CellBuffer {
+    let mut screen = window::CellBuffer::new(height, width);
+    let areas = page.layout.compute(layout::Rect {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    });
+    for (i, (area, widget)) in areas.iter().zip(&mut page.widgets).enumerate() {
+        if !area.can_display_widget() {
+            continue;
+        }
+        let mut window = window::Window::with_backend(
+            *area,
+            window::CellBuffer::new(area.height, area.width),
+            window::CellBuffer::new(area.height - 2, area.width - 4),
+            style,
+            (i + 1).to_string(),
+        );
+        window.write(&widget.plugin.display(area.height - 2, area.width - 4));
+        window.set_title(
+            widget
+                .plugin
+                .title()
+                .unwrap_or_else(|| String::from(&widget.name)),
+        );
+        window.set_border_color(style.border);
 
-    vec![widget1, widget2, widget3, widget4]
+        screen.blit(window.frame(), area.y, area.x);
+        screen.blit(window.content(), area.y + 1, area.x + 2);
+    }
+    screen
 }
 
-fn init_cpuusage_plugin() -> (Box<dyn widget::Widget>, bool) {
+fn init_cpuusage_plugin(
+    options: &serde_json::Value,
+    source: &recording::SampleSource,
+    _capture: &TextCapture,
+) -> (Box<dyn widget::Widget>, bool) {
     (
         Box::new(CpuUsage {
-            data: Vec::new(),
+            source: source.clone(),
+            restarts: 0,
+            data: chart_history(options),
             chart: components::chart::Chart::new(0, 0, None, Some(true), None),
+            zoom: 1,
+            visible_points: 0,
+            visible_peak: None,
             sysinfo: sysinfo::System::new_all(),
-            last_cpu_usage: 0.,
+            color: chart_color(options, "color"),
         }),
         false,
     )
 }
-fn init_memory_plugin() -> (Box<dyn widget::Widget>, bool) {
+fn init_network_plugin(
+    options: &serde_json::Value,
+    source: &recording::SampleSource,
+    _capture: &TextCapture,
+) -> (Box<dyn widget::Widget>, bool) {
+    let mut sysinfo = sysinfo::System::new();
+    sysinfo.refresh_networks_list();
     (
-        Box::new(MemoryUsage {
+        Box::new(NetworkUsage {
+            source: source.clone(),
+            restarts: 0,
+            sysinfo,
+            history: std::collections::BTreeMap::new(),
+            retention: chart_retention(options),
+            selected: 0,
+            chart: components::chart::Chart::new(0, 0, None, Some(false), None),
+            zoom: 1,
+            visible_points: 0,
+            last_update: std::time::Instant::now(),
+            refresh_progress: 0,
+        }),
+        true,
+    )
+}
+fn init_disk_usage_plugin(
+    _options: &serde_json::Value,
+    source: &recording::SampleSource,
+    _capture: &TextCapture,
+) -> (Box<dyn widget::Widget>, bool) {
+    (
+        Box::new(DiskUsage {
+            source: source.clone(),
+            sysinfo: sysinfo::System::new(),
+            disks: vec![],
+            // The disks are listed on the first update
+            refresh_progress: 14,
+        }),
+        false,
+    )
+}
+fn init_disk_io_plugin(
+    options: &serde_json::Value,
+    source: &recording::SampleSource,
+    _capture: &TextCapture,
+) -> (Box<dyn widget::Widget>, bool) {
+    let mut sysinfo = sysinfo::System::new();
+    sysinfo.refresh_processes_specifics(sysinfo::ProcessRefreshKind::new().with_disk_usage());
+    (
+        Box::new(DiskIo {
+            source: source.clone(),
+            restarts: 0,
+            sysinfo,
+            read: chart_history(options),
+            written: chart_history(options),
+            chart: components::chart::Chart::new(0, 0, None, Some(false), None),
+            zoom: 1,
+            visible_points: 0,
+            top_processes: vec![],
+            last_update: std::time::Instant::now(),
+            refresh_progress: 0,
+        }),
+        false,
+    )
+}
+fn init_temperatures_plugin(
+    options: &serde_json::Value,
+    source: &recording::SampleSource,
+    _capture: &TextCapture,
+) -> (Box<dyn widget::Widget>, bool) {
+    let mut sysinfo = sysinfo::System::new();
+    sysinfo.refresh_components_list();
+    (
+        Box::new(Temperatures {
+            source: source.clone(),
+            restarts: 0,
+            sysinfo,
+            sensors: vec![],
+            data: std::collections::HashMap::new(),
+            retention: chart_retention(options),
+            selected: 0,
+            chart: components::chart::Chart::new(0, 0, None, Some(true), Some(String::from("°C"))),
+            zoom: 1,
+            visible_points: 0,
+        }),
+        true,
+    )
+}
+fn init_cpucores_plugin(
+    options: &serde_json::Value,
+    source: &recording::SampleSource,
+    _capture: &TextCapture,
+) -> (Box<dyn widget::Widget>, bool) {
+    (
+        Box::new(CpuCores {
+            source: source.clone(),
+            restarts: 0,
             sysinfo: sysinfo::System::new_all(),
             data: vec![],
-            chart: components::chart::Chart::new(0, 0, None, Some(true), None),
+            retention: chart_retention(options),
+            frequencies: vec![],
+            chart: components::chart::Chart::new(0, 0, None, Some(false), None),
+            zoom: 1,
+            visible_points: 0,
         }),
         false,
     )
 }
-fn init_process_plugin() -> (Box<dyn widget::Widget>, bool) {
+fn init_memory_plugin(
+    options: &serde_json::Value,
+    source: &recording::SampleSource,
+    _capture: &TextCapture,
+) -> (Box<dyn widget::Widget>, bool) {
     (
-        Box::new(ProcessList {
+        Box::new(MemoryUsage {
+            source: source.clone(),
+            restarts: 0,
             sysinfo: sysinfo::System::new_all(),
-            data: vec![],
-            chart: components::listview::ListView::new(
-                0,
-                0,
-                &Vec::new(),
-                String::from("Name"),
-                vec![
-                    String::from("CPU %"),
-                    String::from("Count"),
-                    String::from("Memory %"),
-                ],
-                std::option::Option::from(String::from("Name")),
-                std::option::Option::from(Ordering::Inversed),
-            ),
-            refresh_progress: 6,
-            kill_process_security: false,
+            data: chart_history(options),
+            chart: components::chart::Chart::new(0, 0, None, Some(true), None),
+            swap_data: chart_history(options),
+            swap_chart: components::chart::Chart::new(0, 0, None, Some(true), None),
+            zoom: 1,
+            visible_points: 0,
+            memory: recording::MemoryFrame::default(),
+            color: chart_color(options, "color"),
+            swap_color: chart_color(options, "swap_color"),
         }),
-        true,
+        false,
     )
 }
+fn init_process_plugin(
+    options: &serde_json::Value,
+    source: &recording::SampleSource,
+    capture: &TextCapture,
+) -> (Box<dyn widget::Widget>, bool) {
+    let mut process_list = ProcessList {
+        sysinfo: sysinfo::System::new_all(),
+        source: source.clone(),
+        groups: vec![],
+        data: vec![],
+        chart: components::listview::ListView::new(0, 0, &[], String::new(), vec![], None, None),
+        refresh_progress: 6,
+        signal_prompt: SignalPrompt::Closed,
+        status: None,
+        search: None,
+        search_editing: capture.clone(),
+        mode: ProcessListMode::Grouped,
+        sort_key: String::from("Name"),
+        sort_inversed: true,
+        collapsed_processes: std::collections::HashSet::new(),
+        columns: PROCESS_COLUMNS.into_iter().map(String::from).collect(),
+        visible_columns: vec![],
+        refresh_ticks: 7,
+    };
+
+    match options.get("mode").and_then(serde_json::Value::as_str) {
+        // Only the groups are recorded
+        _ if source.mode() == recording::Mode::Replay => {}
+        Some("tree") => process_list.mode = ProcessListMode::Tree,
+        Some("per_process") => process_list.mode = ProcessListMode::PerProcess,
+        _ => {}
+    }
+    if let Some(columns) = options.get("columns").and_then(serde_json::Value::as_array) {
+        process_list.columns = columns
+            .iter()
+            .filter_map(serde_json::Value::as_str)
+            .filter(|column| PROCESS_COLUMNS.contains(column))
+            .map(String::from)
+            .collect();
+    }
+    if let Some(sort_key) = options.get("sort").and_then(serde_json::Value::as_str) {
+        if PROCESS_GROUP_COLUMNS.contains(&sort_key)
+            || process_list.columns.iter().any(|column| column == sort_key)
+            || sort_key == "Command"
+        {
+            process_list.sort_key = String::from(sort_key);
+        }
+    }
+    process_list.sort_key = match (process_list.mode, process_list.sort_key.as_str()) {
+        (ProcessListMode::PerProcess, "Name") => String::from("Command"),
+        (ProcessListMode::Grouped | ProcessListMode::Tree, "Command") => String::from("Name"),
+        _ => process_list.sort_key,
+    };
+    if let Some(sort_inversed) = options
+        .get("sort_inversed")
+        .and_then(serde_json::Value::as_bool)
+    {
+        process_list.sort_inversed = sort_inversed;
+    }
+    if let Some(refresh_ticks) = options
+        .get("refresh_ticks")
+        .and_then(serde_json::Value::as_u64)
+    {
+        process_list.refresh_ticks = (refresh_ticks as usize).max(1);
+        process_list.refresh_progress = process_list.refresh_ticks - 1;
+    }
+    process_list.visible_columns = process_list.mode_columns().0;
+    process_list.chart = process_list.create_listview();
+
+    (Box::new(process_list), true)
+}
+
+#[cfg(test)]
+mod test_files;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot_style() -> window::WindowStyle {
+        window::WindowStyle {
+            border: ncurses::A_NORMAL(),
+            title: ncurses::A_NORMAL(),
+            text: ncurses::A_NORMAL(),
+            border_style: theme::BorderStyle::Single,
+        }
+    }
+
+    fn widget_page(name: &str, (plugin, focusable): (Box<dyn widget::Widget>, bool)) -> Page {
+        Page {
+            widgets: vec![ScreenWidget {
+                plugin,
+                name: String::from(name),
+                entry: None,
+                focusable,
+                capture: TextCapture::default(),
+            }],
+            focusable_widgets: vec![1],
+            layout: layout::Layout::auto(1),
+            name: None,
+        }
+    }
+
+    #[test]
+    fn unknown_builtin_options_are_reported() {
+        let builtin_addon = builtin_widgets();
+        let mut builtin_names: Vec<&str> = builtin_addon.keys().map(String::as_str).collect();
+        builtin_names.sort_unstable();
+        assert_eq!(
+            BUILTIN_OPTIONS.map(|(name, _)| name).to_vec(),
+            builtin_names
+        );
+
+        let file = test_files::TempFile::new(
+            "options.json",
+            r#"{"pages": [[
+                {"widget": "cpu_chart", "options": {"colour": "red", "history": 60}},
+                {"widget": "disk_usage", "options": {"mode": "tree"}},
+                {"widget": "process_list", "options": {"mode": "tree", "sort": "PID"}}
+            ]]}"#,
+        );
+        let problems = load_config(&file.path, &builtin_addon).err();
+        assert_eq!(
+            problems,
+            Some(vec![
+                String::from(
+                    "page 1: unknown option `colour` for widget `cpu_chart`, its options are `color`, `history`"
+                ),
+                String::from("page 1: widget `disk_usage` takes no option, found `mode`"),
+            ])
+        );
+    }
+
+    #[test]
+    fn invalid_builtin_option_values_are_reported() {
+        let file = test_files::TempFile::new(
+            "values.json",
+            r#"{"pages": [[
+                {"widget": "cpu_chart", "options": {"color": "Red", "history": "1h"}},
+                {"widget": "memory_chart", "options": {"swap_color": "pink", "history": 0}},
+                {"widget": "process_list", "options": {
+                    "mode": "treee",
+                    "sort": "Cpu",
+                    "columns": ["PID", "Pid", 3],
+                    "sort_inversed": "yes",
+                    "refresh_ticks": 0
+                }},
+                {"widget": "process_list", "options": {"columns": ["PID"], "sort": "User"}}
+            ]]}"#,
+        );
+        let problems = load_config(&file.path, &builtin_widgets()).err();
+        let colors = "`red`, `green`, `yellow`, `blue`, `magenta`, `cyan`, `white`, `black`";
+        let columns =
+            "`PID`, `User`, `State`, `Nice`, `Start`, `Elapsed`, `Threads`, `CPU %`, `Memory %`";
+        assert_eq!(
+            problems,
+            Some(vec![
+                String::from("page 1: option `history` of widget `cpu_chart`: invalid value \"1h\" (expected a positive number of updates)"),
+                String::from("page 1: option `history` of widget `memory_chart`: invalid value 0 (expected a positive number of updates)"),
+                format!("page 1: option `swap_color` of widget `memory_chart`: invalid value \"pink\" (expected one of {})", colors),
+                format!("page 1: option `columns` of widget `process_list`: invalid column \"Pid\" (expected one of {})", columns),
+                format!("page 1: option `columns` of widget `process_list`: invalid column 3 (expected one of {})", columns),
+                String::from("page 1: option `mode` of widget `process_list`: invalid value \"treee\" (expected one of `grouped`, `tree`, `per_process`)"),
+                String::from("page 1: option `refresh_ticks` of widget `process_list`: invalid value 0 (expected a positive number of updates)"),
+                String::from("page 1: option `sort` of widget `process_list`: invalid value \"Cpu\" (expected a shown column, one of `Name`, `CPU %`, `Count`, `Memory %`, `Command`, `PID`)"),
+                String::from("page 1: option `sort_inversed` of widget `process_list`: invalid value \"yes\" (expected `true` or `false`)"),
+                String::from("page 1: option `sort` of widget `process_list`: invalid value \"User\" (expected a shown column, one of `Name`, `CPU %`, `Count`, `Memory %`, `Command`, `PID`)"),
+            ])
+        );
+    }
+
+    #[test]
+    fn tree_title_counts_the_shown_rows() {
+        let (mut plugin, _) = init_process_plugin(
+            &serde_json::json!({}),
+            &recording::SampleSource::default(),
+            &TextCapture::default(),
+        );
+        plugin.on_input(String::from("t"));
+        plugin.on_update();
+        let shown_rows = |plugin: &mut Box<dyn widget::Widget>| {
+            plugin
+                .display(1000, 120)
+                .lines()
+                .skip(1)
+                .filter(|line| !line.trim().is_empty())
+                .count()
+        };
+
+        let rows = shown_rows(&mut plugin);
+        assert_eq!(plugin.title(), Some(format!("[{}] Process Tree", rows)));
+        // Folding the first process hides its children
+        plugin.on_input(String::from(" "));
+        let folded_rows = shown_rows(&mut plugin);
+        assert!(folded_rows <= rows);
+        assert_eq!(
+            plugin.title(),
+            Some(format!("[{}] Process Tree", folded_rows))
+        );
+    }
+
+    #[test]
+    fn widgets_sample_from_their_source() {
+        let source = recording::SampleSource::new(recording::Mode::Replay);
+        let mut page = widget_page(
+            "cpu_chart",
+            init_cpuusage_plugin(&serde_json::json!({}), &source, &TextCapture::default()),
+        );
+        source.begin_frame(recording::Frame {
+            cpu: Some(42.),
+            ..recording::Frame::default()
+        });
+        update_widgets(std::slice::from_mut(&mut page));
+        let lines = snapshot_page(&mut page, 6, 30, snapshot_style()).lines();
+        assert!(lines[1].contains(" 42% "), "{:?}", lines);
+
+        source.set_mode(recording::Mode::Recording);
+        source.begin_frame(recording::Frame::default());
+        update_widgets(std::slice::from_mut(&mut page));
+        assert!(source.take_frame().is_some_and(|frame| frame.cpu.is_some()));
+    }
+
+    #[test]
+    fn seeking_starts_the_charts_again() {
+        let source = recording::SampleSource::new(recording::Mode::Replay);
+        let mut cpu_usage = CpuUsage {
+            sysinfo: sysinfo::System::new(),
+            source: source.clone(),
+            restarts: 0,
+            data: history::History::new(100),
+            chart: components::chart::Chart::new(0, 0, None, Some(true), None),
+            zoom: 1,
+            visible_points: 0,
+            visible_peak: None,
+            color: None,
+        };
+        for cpu in [10., 20.] {
+            source.begin_frame(recording::Frame {
+                cpu: Some(cpu),
+                ..recording::Frame::default()
+            });
+            widget::Widget::on_update(&mut cpu_usage);
+        }
+        assert_eq!(chart_points(&cpu_usage.data.window(10, 1)), vec![10, 20]);
+
+        source.restart_charts();
+        source.begin_frame(recording::Frame {
+            cpu: Some(30.),
+            ..recording::Frame::default()
+        });
+        widget::Widget::on_update(&mut cpu_usage);
+        assert_eq!(chart_points(&cpu_usage.data.window(10, 1)), vec![30]);
+    }
+
+    #[test]
+    fn chart_widgets_keep_a_bounded_history() {
+        let source = recording::SampleSource::new(recording::Mode::Replay);
+        let mut page = widget_page(
+            "disk_io",
+            init_disk_io_plugin(
+                &serde_json::json!({ "history": 100 }),
+                &source,
+                &TextCapture::default(),
+            ),
+        );
+        for i in 0..1000 {
+            source.begin_frame(recording::Frame {
+                disk_io: Some(recording::DiskIoFrame {
+                    read: i * 1024,
+                    written: 0,
+                    processes: vec![],
+                }),
+                ..recording::Frame::default()
+            });
+            update_widgets(std::slice::from_mut(&mut page));
+        }
+        let lines = snapshot_page(&mut page, 10, 22, snapshot_style()).lines();
+        assert!(lines[1].contains("Read 999.0K/s"), "{:?}", lines);
+
+        // 40 points are shown, the history keeps 100 of them
+        let plugin = &mut page.widgets[0].plugin;
+        for key in ["(", "(", "("] {
+            plugin.on_input(String::from(key));
+        }
+        assert_eq!(plugin.title().as_deref(), Some("Disk I/O ×4"));
+        for key in [")", ")"] {
+            plugin.on_input(String::from(key));
+        }
+        assert_eq!(plugin.title().as_deref(), Some("Disk I/O"));
+    }
+
+    #[test]
+    fn default_page_focuses_the_process_list() {
+        let option: config::Config = serde_json::from_str("{}").unwrap();
+        let pages = build_pages(
+            &option,
+            &builtin_widgets(),
+            &std::collections::HashMap::new(),
+            &recording::SampleSource::default(),
+            &mut vec![],
+        );
+        // The charts take the zoom keys without being focused
+        let page = &pages[0];
+        assert_eq!(page.focusable_widgets, vec![3]);
+        assert_eq!(page.widgets[2].name, "process_list");
+        assert!(page.widgets[..2]
+            .iter()
+            .all(|widget| is_chart_widget(&widget.name)));
+    }
+
+    #[test]
+    fn process_list_fits_small_windows() {
+        for mode_key in [None, Some("t"), Some("P")] {
+            let (mut plugin, _) = init_process_plugin(
+                &serde_json::json!({}),
+                &recording::SampleSource::default(),
+                &TextCapture::default(),
+            );
+            plugin.on_update();
+            if let Some(mode_key) = mode_key {
+                plugin.on_input(String::from(mode_key));
+            }
+
+            for (width, height) in [(14, 4), (20, 5), (20, 8), (16, 10), (20, 20), (30, 10)] {
+                // Size of the text inside the border of the window
+                let (width, height) = (width - 4, height - 2);
+                let output = plugin.display(height, width);
+                let lines: Vec<String> = output.lines().map(window::strip_markup).collect();
+                assert!(
+                    ["Name", "Command", "Too narrow"]
+                        .iter()
+                        .any(|start| lines[0].starts_with(start)),
+                    "{:?} at {}x{}: {:?}",
+                    mode_key,
+                    width,
+                    height,
+                    lines
+                );
+                assert!(lines
+                    .iter()
+                    .all(|line| line.chars().count() <= width as usize));
+            }
+        }
+    }
+
+    #[test]
+    fn plugin_errors_fit_small_windows() {
+        let mut error = PluginError {
+            message: String::from("the library `libwidget.so` was not found"),
+        };
+        let width = layout::MIN_WINDOW_WIDTH - 4;
+        let output = widget::Widget::display(&mut error, 4, width);
+        assert_eq!(output.lines().last(), Some("An error o"));
+        assert!(output
+            .lines()
+            .all(|line| line.chars().count() <= width as usize));
+    }
+
+    #[test]
+    fn temperature_histories_follow_their_sensor() {
+        let source = recording::SampleSource::new(recording::Mode::Replay);
+        let mut temperatures = Temperatures {
+            sysinfo: sysinfo::System::new(),
+            source: source.clone(),
+            restarts: 0,
+            sensors: vec![],
+            data: std::collections::HashMap::new(),
+            retention: 100,
+            selected: 0,
+            chart: components::chart::Chart::new(0, 0, None, Some(true), None),
+            zoom: 1,
+            visible_points: 0,
+        };
+        let sensor = |label: &str, temperature: f32| recording::SensorFrame {
+            label: String::from(label),
+            temperature,
+            max: temperature,
+            critical: None,
+        };
+        for sensors in [
+            vec![sensor("cpu", 50.), sensor("gpu", 70.), sensor("nvme", 40.)],
+            vec![sensor("gpu", 71.), sensor("cpu", 51.), sensor("nvme", 41.)],
+            vec![sensor("gpu", 72.), sensor("nvme", 42.), sensor("nvme", 30.)],
+            vec![sensor("nvme", 43.), sensor("nvme", 31.)],
+        ] {
+            source.begin_frame(recording::Frame {
+                temperatures: Some(sensors),
+                ..recording::Frame::default()
+            });
+            widget::Widget::on_update(&mut temperatures);
+        }
+
+        let history = |label: &str, occurrence: usize| {
+            temperatures
+                .data
+                .get(&(String::from(label), occurrence))
+                .map(|data| chart_points(&data.window(10, 1)))
+        };
+        assert_eq!(history("cpu", 0), None);
+        assert_eq!(history("gpu", 0), None);
+        // Sensors sharing a label keep their own history, by their order among them
+        assert_eq!(history("nvme", 0), Some(vec![40, 41, 42, 43]));
+        assert_eq!(history("nvme", 1), Some(vec![30, 31]));
+    }
+
+    #[test]
+    fn tree_search_matches_the_names() {
+        assert_eq!(tree_decoration_len("│  ├─ [-] bash  "), "│  ├─ [-] ".len());
+        assert_eq!(tree_decoration_len("[+] systemd"), 4);
+        assert_eq!(tree_decoration_len("init"), 0);
+
+        // The test runs in a process named after the crate, below its parents
+        let (mut plugin, _) = init_process_plugin(
+            &serde_json::json!({}),
+            &recording::SampleSource::default(),
+            &TextCapture::default(),
+        );
+        plugin.on_input(String::from("t"));
+        plugin.on_update();
+        for key in ["/", "^", "r", "t", "o", "p", "^J"] {
+            plugin.on_input(String::from(key));
+        }
+        let output = plugin.display(30, 120);
+        assert!(
+            output.contains("[[EFFECT_COLOR_YELLOW]]rtop[[EFFECT_COLOR_YELLOW]]"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn process_list_without_items() {
+        let (mut plugin, _) = init_process_plugin(
+            &serde_json::json!({}),
+            &recording::SampleSource::default(),
+            &TextCapture::default(),
+        );
+        plugin.on_update();
+        for key in ["/", "#", "#", "^J"] {
+            plugin.on_input(String::from(key));
+        }
+
+        // The processes are those of the system, so the signal key is only checked not to open
+        // its prompt, and nothing is confirmed or reniced
+        for key in ["n", "N", "J", "K", "G", "g", " ", "d", "t", "P"] {
+            plugin.display(10, 40);
+            plugin.on_input(String::from(key));
+            let output = plugin.display(10, 40);
+            assert!(output
+                .lines()
+                .nth(1)
+                .is_none_or(|row| row.trim().is_empty()));
+            assert!(!output.contains("Send a signal"));
+        }
+    }
+
+    /// Page with a process list, whose search prompt takes every key as text
+    fn process_list_page() -> Page {
+        let capture = TextCapture::default();
+        let (plugin, _) = init_process_plugin(
+            &serde_json::json!({}),
+            &recording::SampleSource::default(),
+            &capture,
+        );
+        Page {
+            widgets: vec![ScreenWidget {
+                plugin,
+                name: String::from("process_list"),
+                capture,
+                entry: None,
+                focusable: true,
+            }],
+            focusable_widgets: vec![1],
+            layout: layout::Layout::auto(1),
+            name: None,
+        }
+    }
+
+    #[test]
+    fn search_takes_the_keys_of_the_interface() {
+        let mut page = process_list_page();
+        assert!(!send_captured_key(&mut page, 1, "q"));
+
+        page.widgets[0].plugin.on_input(String::from("/"));
+        for key in ["q", "Z", "^I"] {
+            assert!(send_captured_key(&mut page, 1, key));
+        }
+        let output = page.widgets[0].plugin.display(10, 40);
+        assert_eq!(output.lines().last(), Some("/qZ"));
+
+        // Closing the prompt gives the keys back to the interface
+        assert!(send_captured_key(&mut page, 1, "^J"));
+        assert!(!send_captured_key(&mut page, 1, "q"));
+        assert!(!send_captured_key(&mut page, 0, "q"));
+    }
+
+    #[test]
+    fn search_takes_the_keys_of_the_replay() {
+        let mut page = process_list_page();
+        page.widgets[0].plugin.on_input(String::from("/"));
+        for key in [" ", "[", "]", "<", ">", "{", "}"] {
+            assert!(send_captured_key(&mut page, 1, key));
+        }
+        let output = page.widgets[0].plugin.display(10, 40);
+        assert_eq!(output.lines().last(), Some("/ []<>{}"));
+    }
+
+    #[test]
+    fn search_takes_the_zoom_keys() {
+        let mut page = process_list_page();
+        page.widgets[0].plugin.on_input(String::from("/"));
+        for key in ZOOM_KEYS {
+            assert!(send_captured_key(&mut page, 1, key));
+        }
+        let output = page.widgets[0].plugin.display(10, 40);
+        assert_eq!(output.lines().last(), Some("/()"));
+    }
+}
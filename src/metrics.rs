@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use sysinfo::{CpuExt, ProcessExt, SystemExt};
+
+/// Share of `total` taken by `used`, in percent
+pub fn percent(used: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.
+    } else {
+        used as f64 * 100. / total as f64
+    }
+}
+
+/// Usage of all the CPUs, in percent, since the previous refresh of the CPUs
+pub fn cpu_percent(sysinfo: &sysinfo::System) -> f32 {
+    sysinfo.global_cpu_info().cpu_usage()
+}
+
+/// Usage of each CPU, in percent, since the previous refresh of the CPUs
+pub fn cpu_cores_percent(sysinfo: &sysinfo::System) -> Vec<f32> {
+    sysinfo.cpus().iter().map(CpuExt::cpu_usage).collect()
+}
+
+/// Processes sharing a name, as grouped by the process list. The PIDs are not kept in the
+/// exported metrics nor in recordings
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ProcessGroup {
+    pub name: String,
+    pub count: usize,
+    /// Share of the CPU, in percent of all the physical cores
+    pub cpu_percent: f32,
+    /// Resident memory, in bytes
+    pub memory: u64,
+    pub memory_percent: f64,
+    #[serde(skip)]
+    pub pids: Vec<sysinfo::Pid>,
+}
+
+/// Processes of `sysinfo` grouped by name, sorted by name
+pub fn process_groups(sysinfo: &sysinfo::System) -> Vec<ProcessGroup> {
+    let physical_core_count = sysinfo.physical_core_count().unwrap_or(1).max(1);
+    let mut groups: std::collections::BTreeMap<&str, ProcessGroup> =
+        std::collections::BTreeMap::new();
+
+    for (pid, process) in sysinfo.processes() {
+        let group = groups
+            .entry(process.name())
+            .or_insert_with(|| ProcessGroup {
+                name: String::from(process.name()),
+                count: 0,
+                cpu_percent: 0.,
+                memory: 0,
+                memory_percent: 0.,
+                pids: vec![],
+            });
+        group.count += 1;
+        group.cpu_percent += process.cpu_usage() / physical_core_count as f32;
+        group.memory += process.memory();
+        group.pids.push(*pid);
+    }
+
+    groups
+        .into_values()
+        .map(|group| ProcessGroup {
+            memory_percent: percent(group.memory, sysinfo.total_memory()),
+            ..group
+        })
+        .collect()
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct MemorySample {
+    /// Sizes in bytes
+    pub total: u64,
+    pub used: u64,
+    pub available: u64,
+    pub percent: f64,
+    pub swap_total: u64,
+    pub swap_used: u64,
+    pub swap_percent: f64,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct LoadAverage {
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+}
+
+/// Figures shown by the CPU, memory and process widgets at some point in time
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Sample {
+    /// Local time, in the RFC 3339 format
+    pub timestamp: String,
+    pub cpu_percent: f32,
+    pub cpu_cores_percent: Vec<f32>,
+    pub memory: MemorySample,
+    pub load_average: LoadAverage,
+    pub processes: Vec<ProcessGroup>,
+}
+
+/// Latest sample taken by `start_sampling`, `None` until the first one
+pub type Samples = tokio::sync::watch::Receiver<Option<Sample>>;
+
+/// Take a sample every `interval` in a task of the runtime. The export and the exporter share
+/// its samples, so the system is measured once whoever reads them. The task stops once every
+/// receiver is dropped
+pub fn start_sampling(interval: std::time::Duration) -> Samples {
+    let (sender, samples) = tokio::sync::watch::channel(None);
+    tokio::spawn(async move {
+        let mut sysinfo = sysinfo::System::new_all();
+        loop {
+            // The CPU usage is measured between two refreshes
+            tokio::time::sleep(interval).await;
+            // Refreshing the processes takes a while, which would hold up the other tasks
+            let refreshed = tokio::task::spawn_blocking(move || {
+                Sample::refresh(&mut sysinfo);
+                sysinfo
+            })
+            .await;
+            let Ok(refreshed) = refreshed else {
+                break;
+            };
+            sysinfo = refreshed;
+            if sender.send(Some(Sample::collect(&sysinfo))).is_err() {
+                break;
+            }
+        }
+    });
+    samples
+}
+
+impl Sample {
+    /// Refresh what a sample is made of. CPU usages are computed since the previous refresh,
+    /// so the first sample of a `System` always has none
+    pub fn refresh(sysinfo: &mut sysinfo::System) {
+        sysinfo.refresh_cpu();
+        sysinfo.refresh_memory();
+        sysinfo.refresh_processes();
+    }
+
+    pub fn collect(sysinfo: &sysinfo::System) -> Self {
+        let load_average = sysinfo.load_average();
+        Self {
+            timestamp: chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+            cpu_percent: cpu_percent(sysinfo),
+            cpu_cores_percent: cpu_cores_percent(sysinfo),
+            memory: MemorySample {
+                total: sysinfo.total_memory(),
+                used: sysinfo.used_memory(),
+                available: sysinfo.available_memory(),
+                percent: percent(sysinfo.used_memory(), sysinfo.total_memory()),
+                swap_total: sysinfo.total_swap(),
+                swap_used: sysinfo.used_swap(),
+                swap_percent: percent(sysinfo.used_swap(), sysinfo.total_swap()),
+            },
+            load_average: LoadAverage {
+                one: load_average.one,
+                five: load_average.five,
+                fifteen: load_average.fifteen,
+            },
+            processes: process_groups(sysinfo),
+        }
+    }
+}
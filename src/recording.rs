@@ -0,0 +1,629 @@
+use crate::metrics;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// Frames fed again before the position of a seek, to fill the history of the charts
+pub const SEEK_HISTORY_FRAMES: usize = 500;
+/// Slowest and fastest speeds of a replay
+pub const MIN_SPEED: f64 = 0.25;
+pub const MAX_SPEED: f64 = 64.;
+/// Longest time without frames replayed as it is, such as between two recorded sessions
+const MAX_GAP_MS: i64 = 30_000;
+/// Process groups recorded among the busiest ones for the CPU, and as many for the memory
+pub const RECORDED_PROCESS_GROUPS: usize = 16;
+/// Processes doing I/O recorded, the busiest ones
+pub const RECORDED_IO_PROCESSES: usize = 16;
+
+/// What the built-in widgets measured during an update, one line of a recording. Widgets
+/// absent from the pages or which did not refresh during the update have no sample
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Frame {
+    /// Milliseconds since the Unix epoch
+    pub time: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub load_average: Option<[f64; 3]>,
+    /// Usage of all the CPUs, in percent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cpu_cores: Option<Vec<CoreFrame>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<MemoryFrame>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub networks: Option<NetworkFrame>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disks: Option<Vec<DiskFrame>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disk_io: Option<DiskIoFrame>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperatures: Option<Vec<SensorFrame>>,
+    #[serde(skip_serializing_if = "Option::is_none", with = "compact_groups")]
+    pub processes: Option<Vec<metrics::ProcessGroup>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CoreFrame {
+    /// In percent
+    pub usage: f32,
+    /// In MHz
+    pub frequency: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct MemoryFrame {
+    /// Sizes in bytes
+    pub total: u64,
+    pub used: u64,
+    pub available: u64,
+    pub cached: Option<u64>,
+    pub buffers: Option<u64>,
+    pub swap_total: u64,
+    pub swap_used: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NetworkFrame {
+    /// Milliseconds since the previous sample of the interfaces
+    pub interval: u64,
+    pub interfaces: std::collections::BTreeMap<String, Traffic>,
+}
+
+/// Bytes exchanged by an interface since its previous sample
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Traffic {
+    pub received: u64,
+    pub transmitted: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DiskFrame {
+    pub mount_point: String,
+    /// Sizes in bytes
+    pub total: u64,
+    pub available: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DiskIoFrame {
+    /// Bytes per second of all the processes
+    pub read: u64,
+    pub written: u64,
+    /// Processes doing I/O, from the busiest
+    pub processes: Vec<ProcessIoFrame>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ProcessIoFrame {
+    pub pid: u32,
+    pub name: String,
+    /// Bytes per second
+    pub read: u64,
+    pub written: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SensorFrame {
+    pub label: String,
+    /// In °C
+    pub temperature: f32,
+    pub max: f32,
+    pub critical: Option<f32>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    /// Widgets measure the system
+    Live,
+    /// Widgets measure the system and their samples are kept in the frame
+    Recording,
+    /// Widgets take their samples from the frame
+    Replay,
+}
+
+struct Source {
+    mode: Mode,
+    frame: Option<Frame>,
+    /// Number of times the charts were started again
+    restarts: u64,
+}
+
+/// Where the built-in widgets take their samples from. Each widget keeps a clone of the source
+/// of its interface, clones sharing the mode and the frame of the update
+#[derive(Clone)]
+pub struct SampleSource(std::sync::Arc<std::sync::Mutex<Source>>);
+
+impl Default for SampleSource {
+    fn default() -> Self {
+        Self::new(Mode::Live)
+    }
+}
+
+impl SampleSource {
+    pub fn new(mode: Mode) -> Self {
+        Self(std::sync::Arc::new(std::sync::Mutex::new(Source {
+            mode,
+            frame: None,
+            restarts: 0,
+        })))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Source> {
+        self.0
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    pub fn set_mode(&self, mode: Mode) {
+        self.lock().mode = mode;
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.lock().mode
+    }
+
+    /// Start an update of the widgets: `frame` is filled with their samples when recording,
+    /// and gives them their samples in a replay
+    pub fn begin_frame(&self, frame: Frame) {
+        self.lock().frame = Some(frame);
+    }
+
+    /// Make the widgets start their charts again at their next update, when seeking in a replay
+    pub fn restart_charts(&self) {
+        self.lock().restarts += 1;
+    }
+
+    /// Whether the charts were started again since `restarts`, which is brought up to date
+    pub fn charts_restarted(&self, restarts: &mut u64) -> bool {
+        let current = self.lock().restarts;
+        std::mem::replace(restarts, current) != current
+    }
+
+    /// Frame of the update, once the widgets are updated
+    pub fn take_frame(&self) -> Option<Frame> {
+        self.lock().frame.take()
+    }
+
+    /// Sample of a built-in widget for the current update, `field` being its place in a
+    /// frame. Measured with `measure` unless replaying, `None` if the widget has nothing new
+    pub fn sample<T, F, M>(&self, field: F, measure: M) -> Option<T>
+    where
+        T: Clone,
+        F: FnOnce(&mut Frame) -> &mut Option<T>,
+        M: FnOnce() -> Option<T>,
+    {
+        match self.mode() {
+            Mode::Live => measure(),
+            Mode::Recording => {
+                // Not locked while measuring, which can be slow
+                let sample = measure();
+                if let Some(frame) = &mut self.lock().frame {
+                    // Widgets shown several times record the sample of the first one
+                    let recorded = field(frame);
+                    if recorded.is_none() {
+                        recorded.clone_from(&sample);
+                    }
+                }
+                sample
+            }
+            Mode::Replay => self
+                .lock()
+                .frame
+                .as_mut()
+                .and_then(|frame| field(frame).clone()),
+        }
+    }
+}
+
+/// Process groups written as `[name, count, cpu_percent, memory, memory_percent]` arrays,
+/// with the percentages rounded to a tenth, rather than as objects repeating the field names
+mod compact_groups {
+    use crate::metrics::ProcessGroup;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    type Row = (String, usize, f32, u64, f64);
+
+    fn round(percent: f64) -> f64 {
+        (percent * 10.).round() / 10.
+    }
+
+    pub fn serialize<S: Serializer>(
+        groups: &Option<Vec<ProcessGroup>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        groups
+            .as_ref()
+            .map(|groups| {
+                groups
+                    .iter()
+                    .map(|group| {
+                        (
+                            &group.name,
+                            group.count,
+                            round(f64::from(group.cpu_percent)),
+                            group.memory,
+                            round(group.memory_percent),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<ProcessGroup>>, D::Error> {
+        let rows: Option<Vec<Row>> = Option::deserialize(deserializer)?;
+        Ok(rows.map(|rows| {
+            rows.into_iter()
+                .map(
+                    |(name, count, cpu_percent, memory, memory_percent)| ProcessGroup {
+                        name,
+                        count,
+                        cpu_percent,
+                        memory,
+                        memory_percent,
+                        pids: vec![],
+                    },
+                )
+                .collect()
+        }))
+    }
+}
+
+/// The busiest groups of `groups` for the CPU and for the memory, in their order
+fn busiest_groups(groups: &[metrics::ProcessGroup]) -> Vec<metrics::ProcessGroup> {
+    let busiest = |key: &dyn Fn(&metrics::ProcessGroup) -> f64| {
+        let mut indexes: Vec<usize> = (0..groups.len()).collect();
+        indexes.sort_by(|a, b| key(&groups[*b]).total_cmp(&key(&groups[*a])));
+        indexes.truncate(RECORDED_PROCESS_GROUPS);
+        indexes
+    };
+    let mut kept = busiest(&|group| f64::from(group.cpu_percent));
+    kept.extend(busiest(&|group| group.memory as f64));
+    kept.sort_unstable();
+    kept.dedup();
+    kept.into_iter().map(|i| groups[i].clone()).collect()
+}
+
+/// Writes the frames to a file, one JSON object per line. A frame of the default page takes
+/// about 200 bytes, and up to 1.5 KB more when the process list refreshes, which makes about
+/// 4 MB per hour with the default refresh interval
+pub struct Recorder {
+    file: std::io::BufWriter<std::fs::File>,
+}
+
+impl Recorder {
+    /// Record at the end of the file at `path`, sessions recorded in the same file are replayed
+    /// one after the other
+    pub fn open(path: &str) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self {
+            file: std::io::BufWriter::new(file),
+        })
+    }
+
+    /// Write `frame` without the processes which are not among the busiest ones, flushed right
+    /// away to keep what happened before a crash
+    pub fn write(&mut self, frame: &Frame) -> std::io::Result<()> {
+        let disk_io = frame.disk_io.as_ref().map(|disk_io| DiskIoFrame {
+            processes: disk_io
+                .processes
+                .iter()
+                .take(RECORDED_IO_PROCESSES)
+                .cloned()
+                .collect(),
+            ..disk_io.clone()
+        });
+        let frame = Frame {
+            processes: frame.processes.as_deref().map(busiest_groups),
+            disk_io,
+            ..frame.clone()
+        };
+        serde_json::to_writer(&mut self.file, &frame)?;
+        self.file.write_all(b"\n")?;
+        self.file.flush()
+    }
+}
+
+/// Position, speed and pause of a replay, which gives the frames to feed the widgets with
+pub struct Replay {
+    frames: Vec<Frame>,
+    /// Index of the next frame to feed
+    next: usize,
+    /// Time of the recording reached, in milliseconds since the Unix epoch
+    position: i64,
+    speed: f64,
+    paused: bool,
+    /// Whether the position moved since the last frames given, the charts having to start again
+    seeked: bool,
+}
+
+impl Replay {
+    /// Read the recording at `path`
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let mut frames = vec![];
+        for (i, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            frames.push(
+                serde_json::from_str::<Frame>(line)
+                    .map_err(|error| format!("line {}: {}", i + 1, error))?,
+            );
+        }
+        Self::new(frames).ok_or_else(|| String::from("the recording is empty"))
+    }
+
+    /// Replay of `frames`, `None` without frames
+    pub fn new(mut frames: Vec<Frame>) -> Option<Self> {
+        // The clock may have been set back during the recording
+        frames.sort_by_key(|frame| frame.time);
+        Some(Self {
+            position: frames.first()?.time,
+            frames,
+            next: 0,
+            speed: 1.,
+            paused: false,
+            seeked: false,
+        })
+    }
+
+    /// Frames to feed after `elapsed` of real time, in their order
+    pub fn advance(&mut self, elapsed: std::time::Duration) -> Vec<Frame> {
+        if !self.paused {
+            self.position += (elapsed.as_millis() as f64 * self.speed) as i64;
+            if let Some(next_frame) = self.frames.get(self.next) {
+                let gap = next_frame.time - self.frames[self.next.saturating_sub(1)].time;
+                if gap > MAX_GAP_MS {
+                    self.position = self.position.max(next_frame.time);
+                }
+            }
+            self.position = self.position.min(self.end());
+        }
+
+        let reached = self
+            .frames
+            .partition_point(|frame| frame.time <= self.position);
+        let frames = self.frames[self.next.min(reached)..reached].to_vec();
+        self.next = self.next.max(reached);
+        frames
+    }
+
+    /// Move the position by `offset` milliseconds. Widgets are expected to start their charts
+    /// again, so the frames before the new position are fed again
+    pub fn seek(&mut self, offset: i64) {
+        self.position = (self.position + offset).clamp(self.start(), self.end());
+        let reached = self
+            .frames
+            .partition_point(|frame| frame.time <= self.position);
+        self.next = reached.saturating_sub(SEEK_HISTORY_FRAMES);
+        self.seeked = true;
+    }
+
+    /// Whether the charts have to start again before feeding the next frames, once per seek
+    pub fn take_seek(&mut self) -> bool {
+        std::mem::take(&mut self.seeked)
+    }
+
+    pub fn start(&self) -> i64 {
+        self.frames.first().map_or(0, |frame| frame.time)
+    }
+
+    pub fn end(&self) -> i64 {
+        self.frames.last().map_or(0, |frame| frame.time)
+    }
+
+    pub fn position(&self) -> i64 {
+        self.position
+    }
+
+    /// Last frame fed to the widgets
+    pub fn current_frame(&self) -> Option<&Frame> {
+        self.frames.get(self.next.checked_sub(1)?)
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.frames.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replay of frames at each of `times`, in milliseconds
+    fn replay_of(times: &[i64]) -> Replay {
+        Replay::new(
+            times
+                .iter()
+                .map(|time| Frame {
+                    time: *time,
+                    ..Frame::default()
+                })
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    fn times(frames: &[Frame]) -> Vec<i64> {
+        frames.iter().map(|frame| frame.time).collect()
+    }
+
+    fn millis(millis: u64) -> std::time::Duration {
+        std::time::Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn new_sorts_the_frames() {
+        assert!(Replay::new(vec![]).is_none());
+        let mut replay = replay_of(&[3000, 1000, 2000]);
+        assert_eq!((replay.start(), replay.end()), (1000, 3000));
+        assert_eq!(replay.position(), 1000);
+        assert_eq!(times(&replay.advance(millis(0))), vec![1000]);
+        assert_eq!(times(&replay.advance(millis(1500))), vec![2000]);
+    }
+
+    #[test]
+    fn advance_clamps_at_the_end() {
+        let mut replay = replay_of(&[0, 1000, 2000]);
+        assert_eq!(times(&replay.advance(millis(10_000))), vec![0, 1000, 2000]);
+        assert_eq!(replay.position(), 2000);
+        assert!(replay.is_finished());
+        assert!(replay.advance(millis(1000)).is_empty());
+        assert_eq!(replay.current_frame().map(|frame| frame.time), Some(2000));
+    }
+
+    #[test]
+    fn advance_skips_long_gaps() {
+        let mut replay = replay_of(&[0, 1000, 1000 + MAX_GAP_MS + 1, 1000 + MAX_GAP_MS + 2000]);
+        assert_eq!(times(&replay.advance(millis(1000))), vec![0, 1000]);
+        // The next frame is reached right away, then the time goes on from it
+        assert_eq!(
+            times(&replay.advance(millis(0))),
+            vec![1000 + MAX_GAP_MS + 1]
+        );
+        assert!(replay.advance(millis(1000)).is_empty());
+        assert_eq!(
+            times(&replay.advance(millis(1000))),
+            vec![1000 + MAX_GAP_MS + 2000]
+        );
+
+        let mut replay = replay_of(&[0, MAX_GAP_MS]);
+        replay.advance(millis(0));
+        assert!(replay.advance(millis(1000)).is_empty());
+    }
+
+    #[test]
+    fn pause_and_speed() {
+        let mut replay = replay_of(&[0, 1000, 2000, 3000]);
+        replay.toggle_pause();
+        assert!(replay.is_paused());
+        assert_eq!(times(&replay.advance(millis(5000))), vec![0]);
+        replay.toggle_pause();
+
+        replay.set_speed(2.);
+        assert_eq!(times(&replay.advance(millis(1000))), vec![1000, 2000]);
+        replay.set_speed(1000.);
+        assert_eq!(replay.speed(), MAX_SPEED);
+        replay.set_speed(0.);
+        assert_eq!(replay.speed(), MIN_SPEED);
+        assert_eq!(times(&replay.advance(millis(4000))), vec![3000]);
+    }
+
+    #[test]
+    fn seek_feeds_the_history_again() {
+        let frame_count = SEEK_HISTORY_FRAMES as i64 + 100;
+        let mut replay = replay_of(&(0..frame_count).map(|i| i * 1000).collect::<Vec<i64>>());
+        replay.advance(millis(0));
+
+        assert!(!replay.take_seek());
+        replay.seek(-10_000);
+        assert!(replay.take_seek());
+        assert!(!replay.take_seek());
+        assert_eq!(replay.position(), 0);
+        assert_eq!(times(&replay.advance(millis(0))), vec![0]);
+
+        replay.seek(550_000);
+        let frames = replay.advance(millis(0));
+        assert_eq!(frames.len(), SEEK_HISTORY_FRAMES);
+        assert_eq!(frames.last().map(|frame| frame.time), Some(550_000));
+
+        replay.seek(i64::from(u32::MAX));
+        assert_eq!(replay.position(), replay.end());
+        assert_eq!(replay.advance(millis(0)).len(), SEEK_HISTORY_FRAMES);
+        assert!(replay.is_finished());
+    }
+
+    #[test]
+    fn recordings_keep_the_busiest_processes() {
+        let groups: Vec<metrics::ProcessGroup> = (0..100)
+            .map(|i| metrics::ProcessGroup {
+                name: format!("process {:02}", i),
+                count: 1,
+                cpu_percent: if i % 2 == 0 { i as f32 / 3. } else { 0. },
+                memory: if i % 2 == 0 { 0 } else { 1000 * i },
+                memory_percent: if i % 2 == 0 { 0. } else { i as f64 / 7. },
+                pids: vec![],
+            })
+            .collect();
+        let frame = Frame {
+            time: 1,
+            processes: Some(groups),
+            ..Frame::default()
+        };
+
+        let file = crate::test_files::TempFile::new("record.ndjson", "");
+        let path = file.path.to_string_lossy();
+        let mut recorder = Recorder::open(&path).unwrap();
+        recorder.write(&frame).unwrap();
+        let size = std::fs::metadata(&*path).unwrap().len();
+        let replay = Replay::load(&path);
+
+        assert!(size < 2048, "{} bytes", size);
+        let recorded = replay.unwrap().frames.remove(0).processes.unwrap();
+        assert_eq!(recorded.len(), 2 * RECORDED_PROCESS_GROUPS);
+        assert_eq!(recorded[0].name, "process 68");
+        assert_eq!(recorded[0].cpu_percent, 22.7);
+        assert_eq!(recorded[1].name, "process 69");
+        assert_eq!(recorded[1].memory_percent, 9.9);
+        assert_eq!(recorded.last().map(|group| group.memory), Some(99_000));
+    }
+
+    #[test]
+    fn samples_depend_on_the_mode() {
+        let source = SampleSource::default();
+        let sample = |measured: f32| source.sample(|frame| &mut frame.cpu, || Some(measured));
+
+        assert_eq!(sample(1.), Some(1.));
+        assert_eq!(source.take_frame(), None);
+
+        source.set_mode(Mode::Recording);
+        source.begin_frame(Frame::default());
+        assert_eq!(sample(2.), Some(2.));
+        // The first widget showing the sample records it
+        assert_eq!(sample(3.), Some(3.));
+        assert_eq!(source.take_frame().and_then(|frame| frame.cpu), Some(2.));
+
+        source.set_mode(Mode::Replay);
+        assert_eq!(sample(4.), None);
+        source.begin_frame(Frame {
+            cpu: Some(5.),
+            ..Frame::default()
+        });
+        assert_eq!(sample(6.), Some(5.));
+        assert_eq!(source.clone().mode(), Mode::Replay);
+    }
+
+    #[test]
+    fn restarts_are_seen_once_by_each_widget() {
+        let source = SampleSource::new(Mode::Replay);
+        let (mut first, mut second) = (0, 0);
+        assert!(!source.charts_restarted(&mut first));
+
+        source.restart_charts();
+        assert!(source.clone().charts_restarted(&mut first));
+        assert!(!source.charts_restarted(&mut first));
+        assert!(source.charts_restarted(&mut second));
+    }
+}
@@ -0,0 +1,56 @@
+//! Files written by the tests, for the tests of both the library and the binary which each use
+//! part of them
+#![allow(dead_code)]
+
+/// Path of the temporary directory ending with `name`, the rest of its name being unique to this
+/// path so that tests running at the same time do not share it
+fn unique_path(name: &str) -> std::path::PathBuf {
+    static COUNT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    std::env::temp_dir().join(format!(
+        "rtop-{}-{}-{}",
+        std::process::id(),
+        COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        name
+    ))
+}
+
+/// File of the temporary directory, removed when dropped even if the test fails
+pub struct TempFile {
+    pub path: std::path::PathBuf,
+}
+
+impl TempFile {
+    /// Write `content` to a new file ending with `name`
+    pub fn new(name: &str, content: &str) -> Self {
+        let path = unique_path(name);
+        std::fs::write(&path, content).unwrap();
+        Self { path }
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+/// Directory of the temporary directory, removed with its content when dropped even if the test
+/// fails
+pub struct TempDir {
+    pub path: std::path::PathBuf,
+}
+
+impl TempDir {
+    /// Create a new empty directory ending with `name`
+    pub fn new(name: &str) -> Self {
+        let path = unique_path(name);
+        std::fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.path).ok();
+    }
+}
@@ -0,0 +1,387 @@
+use serde::Deserialize;
+
+/// Names of the eight basic colors, in the order of their ncurses number
+const BASIC_COLORS: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+/// Intensity of the levels of the 6x6x6 color cube of 256-color terminals
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[serde(try_from = "ColorOption")]
+pub enum Color {
+    /// Color of the terminal
+    #[default]
+    Default,
+    /// One of the eight basic colors, as numbered by ncurses
+    Basic(i16),
+    /// Color of the 256-color palette
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorOption {
+    Index(i64),
+    Name(String),
+}
+
+impl TryFrom<ColorOption> for Color {
+    type Error = String;
+
+    fn try_from(option: ColorOption) -> Result<Self, Self::Error> {
+        match option {
+            ColorOption::Index(index) => u8::try_from(index)
+                .map(Self::Indexed)
+                .map_err(|_| format!("unknown color {}, numbers go from 0 to 255", index)),
+            ColorOption::Name(name) => name.parse(),
+        }
+    }
+}
+
+impl std::str::FromStr for Color {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let name = name.to_lowercase();
+        if name == "default" {
+            return Ok(Self::Default);
+        }
+        if let Some(basic) = BASIC_COLORS.iter().position(|color| *color == name) {
+            return Ok(Self::Basic(basic as i16));
+        }
+        if let Ok(index) = name.parse() {
+            return Ok(Self::Indexed(index));
+        }
+
+        let hex = name
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()));
+        if let Some(hex) = hex {
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or_default();
+            return Ok(Self::Rgb(channel(0), channel(2), channel(4)));
+        }
+
+        Err(format!(
+            "unknown color `{}`, expected `default`, {}, a number from 0 to 255 or #rrggbb",
+            name,
+            BASIC_COLORS
+                .iter()
+                .map(|color| format!("`{}`", color))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ))
+    }
+}
+
+impl Color {
+    /// Number of the closest color a terminal with `colors` colors can display, `-1` being the
+    /// default color
+    pub fn to_terminal_color(self, colors: i32) -> i16 {
+        match self {
+            Self::Default => -1,
+            Self::Basic(color) => color,
+            Self::Indexed(index) if colors >= 256 => i16::from(index),
+            // The bright colors are shown as their basic version
+            Self::Indexed(index) if index < 16 => i16::from(index % 8),
+            Self::Indexed(index) => {
+                let (r, g, b) = indexed_to_rgb(index);
+                Self::Rgb(r, g, b).to_terminal_color(colors)
+            }
+            Self::Rgb(r, g, b) if colors >= 256 => i16::from(rgb_to_indexed(r, g, b)),
+            Self::Rgb(r, g, b) => {
+                i16::from(r >= 128) + i16::from(g >= 128) * 2 + i16::from(b >= 128) * 4
+            }
+        }
+    }
+}
+
+/// RGB value of a color of the 256-color palette above the sixteen system colors
+fn indexed_to_rgb(index: u8) -> (u8, u8, u8) {
+    if index >= 232 {
+        let gray = 8 + (index - 232) * 10;
+        return (gray, gray, gray);
+    }
+    let cube = index.saturating_sub(16);
+    (
+        CUBE_LEVELS[usize::from(cube / 36)],
+        CUBE_LEVELS[usize::from(cube / 6 % 6)],
+        CUBE_LEVELS[usize::from(cube % 6)],
+    )
+}
+
+/// Closest color of the 256-color palette, from its color cube or its gray ramp
+fn rgb_to_indexed(r: u8, g: u8, b: u8) -> u8 {
+    let closest_level = |value: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|level| CUBE_LEVELS[*level].abs_diff(value))
+            .unwrap_or_default() as u8
+    };
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        u32::from(r.abs_diff(r2)).pow(2)
+            + u32::from(g.abs_diff(g2)).pow(2)
+            + u32::from(b.abs_diff(b2)).pow(2)
+    };
+
+    let cube = 16 + 36 * closest_level(r) + 6 * closest_level(g) + closest_level(b);
+    let average = ((u16::from(r) + u16::from(g) + u16::from(b)) / 3) as u8;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23);
+
+    if distance(indexed_to_rgb(gray)) < distance(indexed_to_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[serde(try_from = "StyleOption")]
+pub struct Style {
+    pub color: Color,
+    pub bold: bool,
+}
+
+/// A style is written as a color, or as an object to make it bold. Colors are parsed once the
+/// form is known, to report the invalid color rather than the mismatch of the forms
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StyleOption {
+    Color(ColorOption),
+    Style {
+        #[serde(default)]
+        color: Option<ColorOption>,
+        #[serde(default)]
+        bold: bool,
+    },
+}
+
+impl TryFrom<StyleOption> for Style {
+    type Error = String;
+
+    fn try_from(option: StyleOption) -> Result<Self, Self::Error> {
+        match option {
+            StyleOption::Color(color) => Ok(Self {
+                color: color.try_into()?,
+                bold: false,
+            }),
+            StyleOption::Style { color, bold } => Ok(Self {
+                color: color.map(Color::try_from).transpose()?.unwrap_or_default(),
+                bold,
+            }),
+        }
+    }
+}
+
+impl Style {
+    const fn color(color: Color) -> Self {
+        Self { color, bold: false }
+    }
+
+    const fn bold(color: Color) -> Self {
+        Self { color, bold: true }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BorderStyle {
+    /// Line drawing characters of the terminal
+    #[default]
+    Single,
+    Rounded,
+    Double,
+    Heavy,
+    Ascii,
+}
+
+impl BorderStyle {
+    /// Characters of the border: horizontal, vertical, then the top left, top right, bottom
+    /// left and bottom right corners. `None` for the line drawing characters of the terminal
+    pub fn characters(self) -> Option<[&'static str; 6]> {
+        match self {
+            Self::Single => None,
+            Self::Rounded => Some(["─", "│", "╭", "╮", "╰", "╯"]),
+            Self::Double => Some(["═", "║", "╔", "╗", "╚", "╝"]),
+            Self::Heavy => Some(["━", "┃", "┏", "┓", "┗", "┛"]),
+            Self::Ascii => Some(["-", "|", "+", "+", "+", "+"]),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Theme {
+    pub border: Style,
+    pub focused_border: Style,
+    pub title: Style,
+    pub text: Style,
+    /// Default style of the charts of the built-in widgets
+    pub chart: Style,
+    /// Top and bottom bars
+    pub header: Style,
+    /// Errors shown in the bottom bar, like those of the config reload
+    pub error: Style,
+    pub border_style: BorderStyle,
+    /// Whether widgets can color their content with the `[[EFFECT_COLOR_...]]` markup
+    pub widget_colors: bool,
+}
+
+pub const BUILTIN_THEMES: [&str; 4] = ["default", "monochrome", "nord", "gruvbox"];
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            border: Style::color(Color::Basic(2)),
+            focused_border: Style::color(Color::Basic(4)),
+            title: Style::color(Color::Default),
+            text: Style::color(Color::Basic(4)),
+            chart: Style::color(Color::Basic(4)),
+            header: Style::bold(Color::Basic(4)),
+            error: Style::color(Color::Basic(1)),
+            border_style: BorderStyle::Single,
+            widget_colors: true,
+        }
+    }
+}
+
+impl Theme {
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            // For terminals without colors, the focus and the bars are shown in bold
+            "monochrome" => Some(Self {
+                border: Style::color(Color::Default),
+                focused_border: Style::bold(Color::Default),
+                title: Style::color(Color::Default),
+                text: Style::color(Color::Default),
+                chart: Style::color(Color::Default),
+                header: Style::bold(Color::Default),
+                error: Style::bold(Color::Default),
+                border_style: BorderStyle::Single,
+                widget_colors: false,
+            }),
+            "nord" => Some(Self {
+                border: Style::color(Color::Rgb(0x4c, 0x56, 0x6a)),
+                focused_border: Style::color(Color::Rgb(0x88, 0xc0, 0xd0)),
+                title: Style::bold(Color::Rgb(0xec, 0xef, 0xf4)),
+                text: Style::color(Color::Rgb(0xd8, 0xde, 0xe9)),
+                chart: Style::color(Color::Rgb(0x81, 0xa1, 0xc1)),
+                header: Style::bold(Color::Rgb(0x88, 0xc0, 0xd0)),
+                error: Style::bold(Color::Rgb(0xbf, 0x61, 0x6a)),
+                border_style: BorderStyle::Rounded,
+                widget_colors: true,
+            }),
+            "gruvbox" => Some(Self {
+                border: Style::color(Color::Indexed(241)),
+                focused_border: Style::color(Color::Indexed(214)),
+                title: Style::bold(Color::Indexed(223)),
+                text: Style::color(Color::Indexed(250)),
+                chart: Style::color(Color::Indexed(142)),
+                header: Style::bold(Color::Indexed(214)),
+                error: Style::bold(Color::Indexed(167)),
+                border_style: BorderStyle::Single,
+                widget_colors: true,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Theme as written in the config or in a theme file: a base theme with some changes
+#[derive(Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ThemeDefinition {
+    #[serde(default)]
+    pub base: Option<String>,
+    #[serde(default)]
+    pub border: Option<Style>,
+    #[serde(default)]
+    pub focused_border: Option<Style>,
+    #[serde(default)]
+    pub title: Option<Style>,
+    #[serde(default)]
+    pub text: Option<Style>,
+    #[serde(default)]
+    pub chart: Option<Style>,
+    #[serde(default)]
+    pub header: Option<Style>,
+    #[serde(default)]
+    pub error: Option<Style>,
+    #[serde(default)]
+    pub border_style: Option<BorderStyle>,
+    #[serde(default)]
+    pub widget_colors: Option<bool>,
+}
+
+impl ThemeDefinition {
+    /// Apply the changes of the definition to `base`
+    pub fn apply_to(&self, base: Theme) -> Theme {
+        Theme {
+            border: self.border.unwrap_or(base.border),
+            focused_border: self.focused_border.unwrap_or(base.focused_border),
+            title: self.title.unwrap_or(base.title),
+            text: self.text.unwrap_or(base.text),
+            chart: self.chart.unwrap_or(base.chart),
+            header: self.header.unwrap_or(base.header),
+            error: self.error.unwrap_or(base.error),
+            border_style: self.border_style.unwrap_or(base.border_style),
+            widget_colors: self.widget_colors.unwrap_or(base.widget_colors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_by_name_number_or_hex() {
+        assert_eq!("default".parse(), Ok(Color::Default));
+        assert_eq!("Cyan".parse(), Ok(Color::Basic(6)));
+        assert_eq!("208".parse(), Ok(Color::Indexed(208)));
+        assert_eq!("#4C566a".parse(), Ok(Color::Rgb(0x4c, 0x56, 0x6a)));
+        assert!("#4c566".parse::<Color>().is_err());
+        assert!("256".parse::<Color>().is_err());
+        assert!("purple"
+            .parse::<Color>()
+            .is_err_and(|error| error.starts_with("unknown color `purple`")));
+    }
+
+    #[test]
+    fn colors_fall_back_to_the_terminal_palette() {
+        assert_eq!(Color::Default.to_terminal_color(8), -1);
+        assert_eq!(Color::Indexed(208).to_terminal_color(256), 208);
+        assert_eq!(Color::Indexed(9).to_terminal_color(8), 1);
+        assert_eq!(Color::Indexed(196).to_terminal_color(8), 1);
+        assert_eq!(Color::Rgb(0xff, 0x87, 0x00).to_terminal_color(256), 208);
+        assert_eq!(Color::Rgb(0x80, 0x80, 0x80).to_terminal_color(256), 244);
+        assert_eq!(Color::Rgb(0x20, 0xa0, 0xf0).to_terminal_color(8), 6);
+    }
+
+    #[test]
+    fn definitions_change_their_base() {
+        let definition: ThemeDefinition = serde_json::from_str(
+            r##"{"border": "#4c566a", "title": {"color": 214, "bold": true},
+                "header": {"bold": false}, "error": "magenta", "border_style": "ascii"}"##,
+        )
+        .unwrap();
+        let theme = definition.apply_to(Theme::default());
+        assert_eq!(
+            theme,
+            Theme {
+                border: Style::color(Color::Rgb(0x4c, 0x56, 0x6a)),
+                title: Style::bold(Color::Indexed(214)),
+                header: Style::color(Color::Default),
+                error: Style::color(Color::Basic(5)),
+                border_style: BorderStyle::Ascii,
+                ..Theme::default()
+            }
+        );
+
+        assert!(serde_json::from_str::<ThemeDefinition>(r#"{"borders": "red"}"#).is_err());
+        assert!(
+            serde_json::from_str::<ThemeDefinition>(r#"{"text": {"color": "purple"}}"#)
+                .is_err_and(|error| error.to_string().contains("unknown color `purple`"))
+        );
+    }
+}
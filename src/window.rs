@@ -1,255 +1,1103 @@
-use ncurses::*;
-use std::fmt::Write;
+use crate::{layout, theme};
+use ncurses::{
+    attr_t, box_, delwin, derwin, init_pair, newwin, waddstr, wattrset, werase, wmove, wrefresh,
+    COLORS, COLOR_BLACK, COLOR_BLUE, COLOR_CYAN, COLOR_GREEN, COLOR_MAGENTA, COLOR_PAIR,
+    COLOR_PAIRS, COLOR_RED, COLOR_WHITE, COLOR_YELLOW,
+};
 use unicode_segmentation::UnicodeSegmentation;
 
-pub struct Window {
+/// Color pairs and colors created so far, shared by every window as ncurses ones are global
+struct ColorState {
+    /// Foreground and background of each pair, the pair number being the index plus one
+    pairs: Vec<(i16, i16)>,
+    /// Colors usable in the markup as `[[EFFECT_COLOR_<NAME>]]`
+    named_colors: Vec<(String, attr_t)>,
+    /// RGB colors defined in the palette of the terminal, from its last color downward
+    custom_colors: Vec<(u8, u8, u8)>,
+    /// Number and components of the colors of the palette before they were redefined, to give
+    /// them back to the terminal, which may not restore them itself
+    replaced_colors: Vec<(i16, i16, i16, i16)>,
+    enabled: bool,
+    /// Pairs are only recorded, to draw into buffers without a terminal
+    headless: bool,
+}
+
+static COLOR_STATE: std::sync::Mutex<ColorState> = std::sync::Mutex::new(ColorState {
+    pairs: Vec::new(),
+    named_colors: Vec::new(),
+    custom_colors: Vec::new(),
+    replaced_colors: Vec::new(),
+    enabled: true,
+    headless: false,
+});
+
+/// Held by the tests using the colors, which are global
+#[cfg(test)]
+static COLOR_TEST_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Colors available without a terminal
+const HEADLESS_COLORS: i32 = 256;
+/// Color pairs `COLOR_PAIR` can encode in the 8 bits of `A_COLOR`, more wrap to the first pairs
+const MAX_COLOR_PAIRS: i32 = 256;
+
+fn color_state() -> std::sync::MutexGuard<'static, ColorState> {
+    COLOR_STATE
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Create the pairs of the eight basic colors, as pairs 1 to 8 in the ncurses order. Without
+/// `enabled`, colors of the markup are ignored
+pub fn init_colors(enabled: bool) {
+    reset_colors(enabled, false);
+}
+
+/// Like `init_colors`, for drawing into buffers when ncurses is not started, with 256 colors
+pub fn init_headless_colors(enabled: bool) {
+    reset_colors(enabled, true);
+}
+
+/// Start a test from new headless colors, other tests using the colors wait until the returned
+/// guard is dropped
+#[cfg(test)]
+pub(crate) fn isolated_colors(enabled: bool) -> std::sync::MutexGuard<'static, ()> {
+    let guard = COLOR_TEST_LOCK
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    init_headless_colors(enabled);
+    guard
+}
+
+fn reset_colors(enabled: bool, headless: bool) {
+    let mut state = color_state();
+    restore_replaced_colors(&mut state);
+    state.pairs.clear();
+    state.named_colors.clear();
+    state.enabled = enabled;
+    state.headless = headless;
+    drop(state);
+
+    for color in [
+        COLOR_RED,
+        COLOR_GREEN,
+        COLOR_YELLOW,
+        COLOR_BLUE,
+        COLOR_MAGENTA,
+        COLOR_CYAN,
+        COLOR_WHITE,
+        COLOR_BLACK,
+    ] {
+        color_pair(if enabled { color } else { -1 }, -1);
+    }
+}
+
+/// Attribute of the pair of `foreground` and `background`, created if needed. Without free
+/// pair, the default colors are used
+pub fn color_pair(foreground: i16, background: i16) -> attr_t {
+    let mut state = color_state();
+    let pair = if let Some(pair) = state
+        .pairs
+        .iter()
+        .position(|pair| *pair == (foreground, background))
+    {
+        pair + 1
+    } else if (state.pairs.len() as i32) + 1 < available_pairs(&state) {
+        state.pairs.push((foreground, background));
+        if !state.headless {
+            init_pair(state.pairs.len() as i16, foreground, background);
+        }
+        state.pairs.len()
+    } else {
+        0
+    };
+    COLOR_PAIR(pair as i16)
+}
+
+fn available_pairs(state: &ColorState) -> i32 {
+    if state.headless {
+        MAX_COLOR_PAIRS
+    } else {
+        COLOR_PAIRS().min(MAX_COLOR_PAIRS)
+    }
+}
+
+/// Number of colors of the terminal
+fn available_colors() -> i32 {
+    if color_state().headless {
+        HEADLESS_COLORS
+    } else {
+        COLORS()
+    }
+}
+
+/// Foreground and background of the color pair of `attr`, `-1` being the default color
+pub fn pair_colors(attr: attr_t) -> (i16, i16) {
+    let pair = ncurses::PAIR_NUMBER(attr as i32) as usize;
+    color_state()
+        .pairs
+        .get(pair.wrapping_sub(1))
+        .copied()
+        .unwrap_or((-1, -1))
+}
+
+/// Number of the terminal color of `color`. RGB colors are defined in the palette when the
+/// terminal can change it, other colors are replaced by the closest one it can display
+fn terminal_color(color: theme::Color) -> i16 {
+    if let theme::Color::Rgb(r, g, b) = color {
+        if let Some(number) = custom_color((r, g, b)) {
+            return number;
+        }
+    }
+    color.to_terminal_color(available_colors())
+}
+
+/// Number of the color of the palette defined as `rgb`, `None` if the terminal cannot change
+/// its palette or has no color left besides its 16 system colors
+fn custom_color(rgb: (u8, u8, u8)) -> Option<i16> {
+    let mut state = color_state();
+    if state.headless || !ncurses::can_change_color() {
+        return None;
+    }
+
+    let last_color = COLORS().min(i32::from(i16::MAX)) - 1;
+    if let Some(index) = state.custom_colors.iter().position(|color| *color == rgb) {
+        return Some((last_color - index as i32) as i16);
+    }
+    let number = last_color - state.custom_colors.len() as i32;
+    if number < 16 {
+        return None;
+    }
+    let number = number as i16;
+    if !state.replaced_colors.iter().any(|color| color.0 == number) {
+        let (mut r, mut g, mut b) = (0, 0, 0);
+        ncurses::color_content(number, &mut r, &mut g, &mut b);
+        state.replaced_colors.push((number, r, g, b));
+    }
+    // The palette goes from 0 to 1000
+    let scale = |value: u8| (i32::from(value) * 1000 / 255) as i16;
+    ncurses::init_color(number, scale(rgb.0), scale(rgb.1), scale(rgb.2));
+    state.custom_colors.push(rgb);
+    Some(number)
+}
+
+/// Give back to the terminal the colors of its palette redefined by `custom_color`, to call
+/// before ending ncurses
+pub fn restore_palette() {
+    // Called when panicking too, maybe while the state is locked by the same thread
+    let mut state = match COLOR_STATE.try_lock() {
+        Ok(state) => state,
+        Err(std::sync::TryLockError::Poisoned(error)) => error.into_inner(),
+        Err(std::sync::TryLockError::WouldBlock) => return,
+    };
+    restore_replaced_colors(&mut state);
+}
+
+fn restore_replaced_colors(state: &mut ColorState) {
+    for (number, r, g, b) in state.replaced_colors.drain(..) {
+        ncurses::init_color(number, r, g, b);
+    }
+    state.custom_colors.clear();
+}
+
+/// Attribute of `style`, with the color the terminal displays for its color
+pub fn style_attr(style: theme::Style) -> attr_t {
+    let color = color_pair(terminal_color(style.color), -1);
+    if style.bold {
+        color | ncurses::A_BOLD()
+    } else {
+        color
+    }
+}
+
+/// Make `attr` usable in the markup as `[[EFFECT_COLOR_<NAME>]]`
+pub fn define_color(name: &str, attr: attr_t) {
+    let mut state = color_state();
+    state.named_colors.retain(|(color, _)| color != name);
+    state.named_colors.push((String::from(name), attr));
+}
+
+/// Attributes of the parts of a window
+#[derive(Clone, Copy)]
+pub struct WindowStyle {
+    pub border: attr_t,
+    pub title: attr_t,
+    pub text: attr_t,
+    pub border_style: theme::BorderStyle,
+}
+
+/// Surface a window draws on, either a window of ncurses or a buffer in memory
+pub trait Backend {
+    fn erase(&mut self);
+    fn move_cursor(&mut self, y: i32, x: i32);
+    /// Attribute of the text added next
+    fn set_attr(&mut self, attr: attr_t);
+    /// Add `text` at the cursor, going to the next line at the right edge and on `\n`
+    fn add_str(&mut self, text: &str);
+    /// Border of the surface, with the line drawing characters of the terminal
+    fn draw_box(&mut self);
+    fn refresh(&self);
+}
+
+pub struct Curses(ncurses::WINDOW);
+
+impl Backend for Curses {
+    fn erase(&mut self) {
+        werase(self.0);
+    }
+
+    fn move_cursor(&mut self, y: i32, x: i32) {
+        wmove(self.0, y, x);
+    }
+
+    fn set_attr(&mut self, attr: attr_t) {
+        wattrset(self.0, attr);
+    }
+
+    fn add_str(&mut self, text: &str) {
+        waddstr(self.0, text);
+    }
+
+    fn draw_box(&mut self) {
+        box_(self.0, 0, 0);
+    }
+
+    fn refresh(&self) {
+        wrefresh(self.0);
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Cell {
+    /// Grapheme displayed in the cell
+    pub text: String,
+    pub attr: attr_t,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            text: String::from(" "),
+            attr: ncurses::A_NORMAL(),
+        }
+    }
+}
+
+/// Grid of cells in memory. Each grapheme takes one cell, and text going past the last line is
+/// dropped
+#[derive(Clone, Debug)]
+pub struct CellBuffer {
+    pub height: i32,
+    pub width: i32,
+    cells: Vec<Vec<Cell>>,
+    cursor: (i32, i32),
+    attr: attr_t,
+}
+
+impl CellBuffer {
+    pub fn new(height: i32, width: i32) -> Self {
+        Self {
+            height,
+            width,
+            cells: vec![vec![Cell::default(); width.max(0) as usize]; height.max(0) as usize],
+            cursor: (0, 0),
+            attr: ncurses::A_NORMAL(),
+        }
+    }
+
+    pub fn cell(&self, y: i32, x: i32) -> &Cell {
+        &self.cells[y as usize][x as usize]
+    }
+
+    /// Text of each line, without trailing spaces
+    pub fn lines(&self) -> Vec<String> {
+        self.cells
+            .iter()
+            .map(|line| {
+                let text: String = line.iter().map(|cell| cell.text.as_str()).collect();
+                String::from(text.trim_end())
+            })
+            .collect()
+    }
+
+    /// Lines with their attributes as ANSI escape sequences, without trailing spaces
+    pub fn ansi_lines(&self) -> Vec<String> {
+        self.cells
+            .iter()
+            .map(|line| {
+                let end = line
+                    .iter()
+                    .rposition(|cell| *cell != Cell::default())
+                    .map_or(0, |last| last + 1);
+                let mut text = String::new();
+                let mut attr = ncurses::A_NORMAL();
+                for cell in &line[..end] {
+                    if cell.attr != attr {
+                        attr = cell.attr;
+                        text += &ansi_sequence(attr);
+                    }
+                    text += &cell.text;
+                }
+                if attr != ncurses::A_NORMAL() {
+                    text += "\x1b[0m";
+                }
+                text
+            })
+            .collect()
+    }
+
+    /// Copy the cells of `buffer` with their top left corner at `y`, `x`
+    pub fn blit(&mut self, buffer: &Self, y: i32, x: i32) {
+        for (line, cells) in (y..self.height).zip(&buffer.cells) {
+            for (column, cell) in (x..self.width).zip(cells) {
+                if line >= 0 && column >= 0 {
+                    self.cells[line as usize][column as usize] = cell.clone();
+                }
+            }
+        }
+    }
+
+    fn new_line(&mut self) {
+        self.cursor = (self.cursor.0 + 1, 0);
+    }
+}
+
+/// Escape sequence setting the style to `attr` from any previous one
+fn ansi_sequence(attr: attr_t) -> String {
+    let mut codes = vec![String::from("0")];
+    for (effect, code) in [
+        (ncurses::A_BOLD(), "1"),
+        (ncurses::A_DIM(), "2"),
+        (ncurses::A_ITALIC(), "3"),
+        (ncurses::A_UNDERLINE(), "4"),
+        (ncurses::A_REVERSE(), "7"),
+    ] {
+        if attr & effect != 0 {
+            codes.push(String::from(code));
+        }
+    }
+
+    let (foreground, background) = pair_colors(attr);
+    for (color, base, bright_base, extended) in
+        [(foreground, 30, 90, 38), (background, 40, 100, 48)]
+    {
+        match color {
+            0..=7 => codes.push((base + color).to_string()),
+            8..=15 => codes.push((bright_base + color - 8).to_string()),
+            16..=255 => codes.push(format!("{};5;{}", extended, color)),
+            _ => {}
+        }
+    }
+    format!("\x1b[{}m", codes.join(";"))
+}
+
+impl Backend for CellBuffer {
+    fn erase(&mut self) {
+        *self = Self {
+            attr: self.attr,
+            ..Self::new(self.height, self.width)
+        };
+    }
+
+    fn move_cursor(&mut self, y: i32, x: i32) {
+        self.cursor = (y, x);
+    }
+
+    fn set_attr(&mut self, attr: attr_t) {
+        self.attr = attr;
+    }
+
+    fn add_str(&mut self, text: &str) {
+        for grapheme in text.graphemes(true) {
+            let (y, x) = self.cursor;
+            if y < 0 || y >= self.height || x < 0 {
+                return;
+            }
+            if grapheme == "\n" {
+                // Like ncurses, the rest of the line is cleared
+                for cell in &mut self.cells[y as usize][x.min(self.width) as usize..] {
+                    *cell = Cell {
+                        attr: self.attr,
+                        ..Cell::default()
+                    };
+                }
+                self.new_line();
+                continue;
+            }
+
+            self.cells[y as usize][x as usize] = Cell {
+                text: String::from(grapheme),
+                attr: self.attr,
+            };
+            self.cursor.1 += 1;
+            if self.cursor.1 >= self.width {
+                self.new_line();
+            }
+        }
+    }
+
+    fn draw_box(&mut self) {
+        if self.height < 2 || self.width < 2 {
+            return;
+        }
+        let line = "─".repeat(self.width as usize - 2);
+        self.move_cursor(0, 0);
+        self.add_str(&format!("┌{}┐", line));
+        for y in 1..self.height - 1 {
+            self.move_cursor(y, 0);
+            self.add_str("│");
+            self.move_cursor(y, self.width - 1);
+            self.add_str("│");
+        }
+        self.move_cursor(self.height - 1, 0);
+        self.add_str(&format!("└{}┘", line));
+    }
+
+    fn refresh(&self) {}
+}
+
+pub struct Window<B: Backend = Curses> {
     pub height: i32,
     pub width: i32,
     pub x: i32,
     pub y: i32,
-    curse_window: ncurses::WINDOW,
-    inner_window: ncurses::WINDOW,
+    frame: B,
+    content: B,
     text_color: attr_t,
     border_color: attr_t,
+    title_color: attr_t,
+    border_style: theme::BorderStyle,
     title: String,
 }
 
 impl Window {
-    pub fn new(
-        height: i32,
-        width: i32,
-        x: i32,
-        y: i32,
-        border_color: attr_t,
-        text_color: attr_t,
+    pub fn new(height: i32, width: i32, x: i32, y: i32, style: WindowStyle, title: String) -> Self {
+        let frame = newwin(height, width, y, x);
+        let content = derwin(frame, height - 2, width - 4, 1, 2);
+        Self::with_backend(
+            layout::Rect {
+                x,
+                y,
+                width,
+                height,
+            },
+            Curses(frame),
+            Curses(content),
+            style,
+            title,
+        )
+    }
+
+    pub fn deplace(&mut self, x: i32, y: i32) {
+        delwin(self.frame.0);
+        delwin(self.content.0);
+        self.frame = Curses(newwin(self.height, self.width, y, x));
+        self.content = Curses(derwin(self.frame.0, self.height - 2, self.width - 2, 1, 1));
+        self.content.set_attr(self.text_color);
+        self.draw_border();
+    }
+}
+
+impl<B: Backend> Window<B> {
+    /// Window covering `area`, drawing its border and title on `frame`, and its content on
+    /// `content`, which is 2 cells shorter and 4 cells narrower
+    pub fn with_backend(
+        area: layout::Rect,
+        frame: B,
+        content: B,
+        style: WindowStyle,
         title: String,
     ) -> Self {
-        let win_box = newwin(height, width, y, x);
-        let win_inner = derwin(win_box, height - 2, width - 4, 1, 2);
-        let new_win = Self {
-            height,
-            width,
-            x,
-            y,
-            curse_window: win_box,
-            inner_window: win_inner,
-            text_color,
-            border_color,
+        let mut new_win = Self {
+            height: area.height,
+            width: area.width,
+            x: area.x,
+            y: area.y,
+            frame,
+            content,
+            text_color: style.text,
+            border_color: style.border,
+            title_color: style.title,
+            border_style: style.border_style,
             title,
         };
-        wattrset(new_win.inner_window, text_color);
+        new_win.content.set_attr(new_win.text_color);
         new_win.draw_border();
         new_win
     }
 
+    pub fn frame(&self) -> &B {
+        &self.frame
+    }
+
+    pub fn content(&self) -> &B {
+        &self.content
+    }
+
     pub fn refresh(&self) {
-        wrefresh(self.curse_window);
-        wrefresh(self.inner_window);
+        self.frame.refresh();
+        self.content.refresh();
     }
 
-    pub fn write(&self, content: &str) {
-        werase(self.inner_window);
+    pub fn write(&mut self, content: &str) {
+        self.content.erase();
 
-        let mut color_applied = vec![];
-        let mut effect_applied = vec![];
+        let content_width = (self.width - 4).max(0) as usize;
+        let mut effects = ActiveEffects::default();
+        let mut line_width = 0;
+        for token in tokenize(content) {
+            match token {
+                Token::Text(text) => {
+                    for (i, line) in text.split('\n').enumerate() {
+                        // Lines filling the last row they wrap on already moved the cursor to
+                        // the next one
+                        let wrapped =
+                            content_width > 0 && line_width > 0 && line_width % content_width == 0;
+                        if i > 0 && !wrapped {
+                            self.content.add_str("\n");
+                        }
+                        if i > 0 {
+                            line_width = 0;
+                        }
+                        self.content.add_str(line);
+                        line_width += line.graphemes(true).count();
+                    }
+                }
+                token => {
+                    effects.apply(token);
+                    self.content.set_attr(effects.attr(self.text_color));
+                }
+            }
+        }
 
-        let mut trimmed_text = String::new();
-        let mut formated_string = String::new();
+        self.content.set_attr(self.text_color);
+    }
 
-        for part in content.split("[[EFFECT_").collect::<Vec<&str>>() {
-            if part.contains("]]") {
-                let effect: Vec<&str> = part.split("]]").collect();
+    fn draw_border(&mut self) {
+        self.frame.set_attr(self.border_color);
+        if let Some([horizontal, vertical, top_left, top_right, bottom_left, bottom_right]) =
+            self.border_style.characters()
+        {
+            let line = horizontal.repeat((self.width - 2).max(0) as usize);
+            self.frame.move_cursor(0, 0);
+            self.frame
+                .add_str(&format!("{}{}{}", top_left, line, top_right));
+            for y in 1..self.height - 1 {
+                self.frame.move_cursor(y, 0);
+                self.frame.add_str(vertical);
+                self.frame.move_cursor(y, self.width - 1);
+                self.frame.add_str(vertical);
+            }
+            self.frame.move_cursor(self.height - 1, 0);
+            self.frame
+                .add_str(&format!("{}{}{}", bottom_left, line, bottom_right));
+        } else {
+            self.frame.draw_box();
+        }
+        self.frame.set_attr(ncurses::A_NORMAL());
+        self.frame.move_cursor(0, 2);
+        self.frame.add_str(" ");
+        self.frame.set_attr(self.title_color);
 
-                let aa: String = part.chars().skip(effect[0].len() + 2).collect();
-                trimmed_text += &aa;
-            } else {
-                trimmed_text += part;
+        // The title is cut to leave a line before the right corner
+        let mut title_width = (self.width - 6).max(0) as usize;
+        let mut effects = ActiveEffects::default();
+        for token in tokenize(&self.title) {
+            match token {
+                Token::Text(text) => {
+                    let text: String = text.graphemes(true).take(title_width).collect();
+                    title_width -= text.graphemes(true).count();
+                    self.frame.add_str(&text);
+                }
+                token => {
+                    effects.apply(token);
+                    self.frame.set_attr(effects.attr(self.title_color));
+                }
             }
         }
 
-        for (i, line) in trimmed_text.split('\n').into_iter().enumerate() {
-            if line.graphemes(true).count() == self.width as usize - 4 && line.ends_with('\n') {
-                let tmp = content.split('\n').collect::<Vec<&str>>()[i].split("");
+        self.frame.set_attr(ncurses::A_NORMAL());
+        self.frame.add_str(" ");
+    }
 
-                formated_string += &tmp
-                    .take(line.graphemes(true).count() - 1)
-                    .into_iter()
-                    .collect::<String>();
-            } else if line.graphemes(true).count() == self.width as usize - 4 {
-                formated_string += content.split('\n').collect::<Vec<&str>>()[i];
-            } else {
-                writeln!(
-                    &mut formated_string,
-                    "{}",
-                    content.split('\n').collect::<Vec<&str>>()[i]
-                )
-                .unwrap();
+    pub fn resize(&mut self, height: i32, width: i32) {
+        self.height = height;
+        self.width = width;
+    }
+
+    pub fn set_title(&mut self, title: String) {
+        self.title = title;
+    }
+
+    pub fn set_border_color(&mut self, border_color: attr_t) {
+        self.border_color = border_color;
+        self.draw_border();
+    }
+}
+
+/// Effect of the `[[EFFECT_<NAME>]]` markup
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Effect {
+    Bold,
+    Italic,
+    Dimmed,
+    Underline,
+    Reverse,
+    /// `COLOR_<FOREGROUND>` or `COLOR_<FOREGROUND>_<BACKGROUND>`, each color being a name, a
+    /// number of the 256-color palette or `#RRGGBB`
+    Color {
+        foreground: theme::Color,
+        background: theme::Color,
+    },
+    /// `COLOR_<NAME>` of a color given to `define_color`
+    NamedColor(String),
+}
+
+impl Effect {
+    /// Effect of `name`, the text between `EFFECT_` and `]]`
+    pub fn parse(name: &str) -> std::option::Option<Self> {
+        let Some(color) = name.strip_prefix("COLOR_") else {
+            return match name {
+                "BOLD" => Some(Self::Bold),
+                "ITALIC" => Some(Self::Italic),
+                "DIMMED" => Some(Self::Dimmed),
+                "UNDERLINE" => Some(Self::Underline),
+                "REVERSE" => Some(Self::Reverse),
+                _ => None,
+            };
+        };
+
+        if let Ok(foreground) = color.parse() {
+            return Some(Self::Color {
+                foreground,
+                background: theme::Color::Default,
+            });
+        }
+        if let Some((foreground, background)) = color.split_once('_') {
+            if let (Ok(foreground), Ok(background)) = (foreground.parse(), background.parse()) {
+                return Some(Self::Color {
+                    foreground,
+                    background,
+                });
             }
         }
+        Some(Self::NamedColor(String::from(color)))
+    }
 
-        for el in formated_string.split("[[EFFECT_").collect::<Vec<&str>>() {
-            if el.contains("]]") {
-                let effect: Vec<&str> = el.split("]]").collect();
-
-                let attr = get_attr_from_string(effect[0]);
-                if let Some(attr) = attr {
-                    if effect[0].starts_with("COLOR") {
-                        if !color_applied.is_empty()
-                            && color_applied[color_applied.len() - 1] == attr
-                        {
-                            color_applied.pop();
-                        } else {
-                            color_applied.push(attr);
-                        }
-                    } else if !effect_applied.is_empty() {
-                        effect_applied.pop();
-                    } else {
-                        effect_applied.push(attr);
-                    }
-                }
+    pub fn is_color(&self) -> bool {
+        matches!(self, Self::Color { .. } | Self::NamedColor(_))
+    }
 
-                if effect_applied.is_empty() {
-                    wattr_off(self.inner_window, A_ATTRIBUTES());
-                } else {
-                    wattr_on(self.inner_window, effect_applied[effect_applied.len() - 1]);
-                }
-                if color_applied.is_empty() {
-                    wattr_on(self.inner_window, self.text_color);
+    fn attr(&self) -> attr_t {
+        match self {
+            Self::Bold => ncurses::A_BOLD(),
+            Self::Italic => ncurses::A_ITALIC(),
+            Self::Dimmed => ncurses::A_DIM(),
+            Self::Underline => ncurses::A_UNDERLINE(),
+            Self::Reverse => ncurses::A_REVERSE(),
+            Self::Color {
+                foreground,
+                background,
+            } => {
+                if color_state().enabled {
+                    color_pair(terminal_color(*foreground), terminal_color(*background))
                 } else {
-                    wattr_on(self.inner_window, color_applied[color_applied.len() - 1]);
+                    COLOR_PAIR(0)
                 }
-                let aa: String = el.chars().skip(effect[0].len() + 2).collect();
-                waddstr(self.inner_window, &aa);
-            } else {
-                waddstr(self.inner_window, el);
             }
+            // Unknown names have no effect
+            Self::NamedColor(name) => color_state()
+                .named_colors
+                .iter()
+                .find(|(color, _)| color == name)
+                .map_or(ncurses::A_NORMAL(), |(_, attr)| *attr),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Token<'a> {
+    Text(&'a str),
+    /// `[[EFFECT_<NAME>]]`, which closes the effect instead if it is already applied
+    Open(Effect),
+    /// `[[/EFFECT_<NAME>]]`, or `[[/EFFECT]]` to close the last applied effect
+    Close(std::option::Option<Effect>),
+}
+
+/// Split `markup` into text and effects. `\[[` is a literal `[[`, tags of unknown effects are
+/// dropped and anything else is text
+pub fn tokenize(markup: &str) -> Vec<Token<'_>> {
+    let mut tokens = vec![];
+    let mut text_start = 0;
+    let mut position = 0;
+
+    while let Some(found) = markup[position..].find("[[") {
+        let start = position + found;
+        if start > text_start && markup[..start].ends_with('\\') {
+            if start - 1 > text_start {
+                tokens.push(Token::Text(&markup[text_start..start - 1]));
+            }
+            text_start = start;
+            position = start + 2;
+            continue;
         }
 
-        wattrset(self.inner_window, self.text_color);
+        let tag = &markup[start + 2..];
+        let (closing, tag) = match tag.strip_prefix('/') {
+            Some(tag) => (true, tag),
+            None => (false, tag),
+        };
+        let name = tag
+            .find("]]")
+            .map(|end| &tag[..end])
+            .filter(|name| name.starts_with("EFFECT_") || (closing && *name == "EFFECT"));
+        let Some(name) = name else {
+            position = start + 1;
+            continue;
+        };
+
+        if start > text_start {
+            tokens.push(Token::Text(&markup[text_start..start]));
+        }
+        if name == "EFFECT" {
+            tokens.push(Token::Close(None));
+        } else if let Some(effect) = Effect::parse(&name["EFFECT_".len()..]) {
+            tokens.push(if closing {
+                Token::Close(Some(effect))
+            } else {
+                Token::Open(effect)
+            });
+        }
+        text_start = markup.len() - tag.len() + name.len() + 2;
+        position = text_start;
+    }
+    if text_start < markup.len() {
+        tokens.push(Token::Text(&markup[text_start..]));
     }
+    tokens
+}
 
-    fn draw_border(&self) {
-        wattron(self.curse_window, self.border_color);
-        box_(self.curse_window, 0, 0);
-        wattroff(self.curse_window, self.border_color);
-        mvwaddstr(self.curse_window, 0, 2, " ");
+/// `text` without its markup
+pub fn strip_markup(text: &str) -> String {
+    tokenize(text)
+        .into_iter()
+        .filter_map(|token| match token {
+            Token::Text(text) => Some(text),
+            _ => None,
+        })
+        .collect()
+}
 
-        let mut color_applied = vec![];
-        let mut effect_applied = vec![];
-        for el in self.title.split("[[EFFECT_").collect::<Vec<&str>>() {
-            if el.contains("]]") {
-                let effect: Vec<&str> = el.split("]]").collect();
+/// Escape `text` so that it is displayed as is
+pub fn escape_markup(text: &str) -> String {
+    text.replace("[[", "\\[[")
+}
 
-                let attr = get_attr_from_string(effect[0]);
-                if let Some(attr) = attr {
-                    if effect[0].starts_with("COLOR") {
-                        if !color_applied.is_empty()
-                            && color_applied[color_applied.len() - 1] == attr
-                        {
-                            color_applied.pop();
-                        } else {
-                            color_applied.push(attr);
-                        }
-                    } else if !effect_applied.is_empty() {
-                        effect_applied.pop();
-                    } else {
-                        effect_applied.push(attr);
-                    }
-                }
+/// Effects applied at some point of the markup, in the order they were opened
+#[derive(Default, Debug)]
+pub struct ActiveEffects(Vec<Effect>);
 
-                if effect_applied.is_empty() {
-                    wattr_off(self.curse_window, A_ATTRIBUTES());
+impl ActiveEffects {
+    pub fn apply(&mut self, token: Token) {
+        match token {
+            Token::Text(_) => {}
+            // Widgets wrap text between two identical tags, the second one closing the effect
+            Token::Open(effect) => {
+                if let Some(i) = self.0.iter().rposition(|active| *active == effect) {
+                    self.0.remove(i);
                 } else {
-                    wattr_on(self.curse_window, effect_applied[effect_applied.len() - 1]);
+                    self.0.push(effect);
                 }
-                if color_applied.is_empty() {
-                    wattr_off(self.curse_window, self.border_color);
-                } else {
-                    wattr_on(self.curse_window, color_applied[color_applied.len() - 1]);
+            }
+            Token::Close(Some(effect)) => {
+                if let Some(i) = self.0.iter().rposition(|active| *active == effect) {
+                    self.0.remove(i);
                 }
+            }
+            Token::Close(None) => {
+                self.0.pop();
+            }
+        }
+    }
+
+    pub fn effects(&self) -> &[Effect] {
+        &self.0
+    }
 
-                let a: String = el.chars().skip(effect[0].len() + 2).collect();
-                waddstr(self.curse_window, &a);
+    /// Attribute of `base` with the effects applied, the last opened color replacing the others
+    fn attr(&self, base: attr_t) -> attr_t {
+        self.0.iter().fold(base, |attr, effect| {
+            if effect.is_color() {
+                (attr & !ncurses::A_COLOR()) | effect.attr()
             } else {
-                waddstr(self.curse_window, el);
+                attr | effect.attr()
             }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style() -> WindowStyle {
+        WindowStyle {
+            border: ncurses::A_NORMAL(),
+            title: ncurses::A_BOLD(),
+            text: ncurses::A_NORMAL(),
+            border_style: theme::BorderStyle::Single,
         }
+    }
 
-        waddstr(self.curse_window, " ");
-        wattrset(self.curse_window, ncurses::A_NORMAL());
-        wattron(self.curse_window, self.border_color);
+    fn window(height: i32, width: i32, title: &str) -> Window<CellBuffer> {
+        Window::with_backend(
+            layout::Rect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            },
+            CellBuffer::new(height, width),
+            CellBuffer::new(height - 2, width - 4),
+            style(),
+            String::from(title),
+        )
     }
 
-    pub fn resize(&mut self, height: i32, width: i32) {
-        self.height = height;
-        self.width = width;
+    /// The frame with the content drawn inside
+    fn screen(window: &Window<CellBuffer>) -> Vec<String> {
+        let mut screen = window.frame().clone();
+        screen.blit(window.content(), 1, 2);
+        screen.lines()
     }
 
-    pub fn deplace(&mut self, x: i32, y: i32) {
-        delwin(self.curse_window);
-        delwin(self.inner_window);
-        self.curse_window = newwin(self.height, self.width, y, x);
-        self.inner_window = derwin(self.curse_window, self.height - 2, self.width - 2, 1, 1);
-        wattron(self.inner_window, self.text_color);
-        self.draw_border();
+    #[test]
+    fn tokenize_text_and_effects() {
+        assert_eq!(tokenize("plain"), vec![Token::Text("plain")]);
+        assert_eq!(
+            tokenize("a[[EFFECT_BOLD]]b[[/EFFECT_BOLD]]c[[/EFFECT]]"),
+            vec![
+                Token::Text("a"),
+                Token::Open(Effect::Bold),
+                Token::Text("b"),
+                Token::Close(Some(Effect::Bold)),
+                Token::Text("c"),
+                Token::Close(None),
+            ]
+        );
     }
 
-    pub fn set_title(&mut self, title: String) {
-        self.title = title;
+    #[test]
+    fn tokenize_colors() {
+        assert_eq!(
+            tokenize("[[EFFECT_COLOR_RED]][[EFFECT_COLOR_208_#0000FF]][[EFFECT_COLOR_CHART]]"),
+            vec![
+                Token::Open(Effect::Color {
+                    foreground: theme::Color::Basic(1),
+                    background: theme::Color::Default,
+                }),
+                Token::Open(Effect::Color {
+                    foreground: theme::Color::Indexed(208),
+                    background: theme::Color::Rgb(0, 0, 255),
+                }),
+                Token::Open(Effect::NamedColor(String::from("CHART"))),
+            ]
+        );
     }
 
-    pub fn set_border_color(&mut self, border_color: attr_t) {
-        self.border_color = border_color;
-        self.draw_border();
+    #[test]
+    fn tokenize_literal_brackets() {
+        assert_eq!(
+            tokenize("\\[[EFFECT_BOLD]] [[x]] [[[EFFECT_BOLD]]"),
+            vec![
+                Token::Text("[[EFFECT_BOLD]] [[x]] ["),
+                Token::Open(Effect::Bold),
+            ]
+        );
+        assert_eq!(
+            tokenize("[[EFFECT_UNKNOWN]]a [[EFFECT_BOLD"),
+            vec![Token::Text("a [[EFFECT_BOLD"),]
+        );
+        assert_eq!(
+            strip_markup(&escape_markup("[[EFFECT_BOLD]]")),
+            "[[EFFECT_BOLD]]"
+        );
     }
-}
 
-fn get_attr_from_string(attribute: &str) -> std::option::Option<attr_t> {
-    let mut tmp = attribute.chars().collect::<Vec<char>>();
-    tmp.retain(|&c| c == '_');
+    #[test]
+    fn effects_combine_and_close() {
+        let _colors = isolated_colors(true);
+        let mut effects = ActiveEffects::default();
+        for token in tokenize("[[EFFECT_REVERSE]][[EFFECT_BOLD]][[EFFECT_UNDERLINE]]") {
+            effects.apply(token);
+        }
+        assert_eq!(
+            effects.effects(),
+            [Effect::Reverse, Effect::Bold, Effect::Underline]
+        );
 
-    if attribute.starts_with("COLOR_") && tmp.len() == 2 {
-        let foreground = match attribute.split('_').collect::<Vec<&str>>()[1] {
-            "RED" => COLOR_RED,
-            "GREEN" => COLOR_GREEN,
-            "YELLOW" => COLOR_YELLOW,
-            "BLUE" => COLOR_BLUE,
-            "MAGENTA" => COLOR_MAGENTA,
-            "CYAN" => COLOR_CYAN,
-            "WHITE" => COLOR_WHITE,
-            "BLACK" => COLOR_BLACK,
-            _ => -1,
-        };
-        let background = match attribute.split('_').collect::<Vec<&str>>()[2] {
-            "RED" => COLOR_RED,
-            "GREEN" => COLOR_GREEN,
-            "YELLOW" => COLOR_YELLOW,
-            "BLUE" => COLOR_BLUE,
-            "MAGENTA" => COLOR_MAGENTA,
-            "CYAN" => COLOR_CYAN,
-            "WHITE" => COLOR_WHITE,
-            "BLACK" => COLOR_BLACK,
-            _ => -1,
-        };
+        for token in tokenize("[[EFFECT_BOLD]][[/EFFECT]]") {
+            effects.apply(token);
+        }
+        assert_eq!(effects.effects(), [Effect::Reverse]);
+        assert_eq!(effects.attr(ncurses::A_NORMAL()), ncurses::A_REVERSE());
+    }
 
-        init_pair(foreground * 10 + background, foreground, background);
-        std::option::Option::from(COLOR_PAIR(foreground * 10 + background))
-    } else {
-        match attribute {
-            "REVERSE" => std::option::Option::from(ncurses::A_REVERSE()),
-            "BOLD" => std::option::Option::from(ncurses::A_BOLD()),
-            "ITALIC" => std::option::Option::from(ncurses::A_ITALIC()),
-            "DIMMED" => std::option::Option::from(ncurses::A_DIM()),
-            "UNDERLINE" => std::option::Option::from(ncurses::A_UNDERLINE()),
-            "COLOR_RED" => std::option::Option::from(COLOR_PAIR(1)),
-            "COLOR_GREEN" => std::option::Option::from(COLOR_PAIR(2)),
-            "COLOR_YELLOW" => std::option::Option::from(COLOR_PAIR(3)),
-            "COLOR_BLUE" => std::option::Option::from(COLOR_PAIR(4)),
-            "COLOR_MAGENTA" => std::option::Option::from(COLOR_PAIR(5)),
-            "COLOR_CYAN" => std::option::Option::from(COLOR_PAIR(6)),
-            "COLOR_WHITE" => std::option::Option::from(COLOR_PAIR(7)),
-            "COLOR_BLACK" => std::option::Option::from(COLOR_PAIR(8)),
-            _ => std::option::Option::None,
+    #[test]
+    fn color_pairs_fit_in_the_attributes() {
+        let _colors = isolated_colors(true);
+        // The basic colors take pairs 1 to 8, the others go up to 255, the last pair
+        // `COLOR_PAIR` can encode
+        for background in 0..247 {
+            assert_eq!(
+                pair_colors(color_pair(COLOR_WHITE, background)),
+                (COLOR_WHITE, background)
+            );
         }
+        assert_eq!(color_pair(COLOR_WHITE, 246), COLOR_PAIR(255));
+
+        // The 256th pair cannot be encoded, the default colors are used
+        assert_eq!(color_pair(COLOR_RED, 0), COLOR_PAIR(0));
+        assert_eq!(color_pair(COLOR_RED, -1), COLOR_PAIR(1));
+    }
+
+    #[test]
+    fn write_applies_effects() {
+        let _colors = isolated_colors(true);
+        let mut window = window(4, 14, "");
+        window.write("a[[EFFECT_BOLD]]b[[EFFECT_UNDERLINE]]c[[EFFECT_BOLD]]d");
+
+        let attrs: Vec<attr_t> = (0..4).map(|x| window.content().cell(0, x).attr).collect();
+        assert_eq!(window.content().lines()[0], "abcd");
+        assert_eq!(
+            attrs,
+            [
+                ncurses::A_NORMAL(),
+                ncurses::A_BOLD(),
+                ncurses::A_BOLD() | ncurses::A_UNDERLINE(),
+                ncurses::A_UNDERLINE(),
+            ]
+        );
+    }
+
+    #[test]
+    fn write_wraps_at_content_width() {
+        let _colors = isolated_colors(true);
+        let mut window = window(6, 14, "");
+        // The first line fills the 10 cells of the content and must not leave an empty line
+        window.write("0123456789\nnext\n0123456789ab");
+
+        assert_eq!(
+            window.content().lines(),
+            ["0123456789", "next", "0123456789", "ab"]
+        );
+    }
+
+    #[test]
+    fn write_wraps_lines_several_times_wider() {
+        let _colors = isolated_colors(true);
+        let mut window = window(7, 8, "");
+        window.write("01234567\n\nab");
+
+        assert_eq!(window.content().lines(), ["0123", "4567", "", "ab", ""]);
+    }
+
+    #[test]
+    fn write_counts_graphemes() {
+        let _colors = isolated_colors(true);
+        let mut window = window(5, 9, "");
+        window.write("e\u{301}te\u{301}!\nnext");
+
+        assert_eq!(window.content().lines(), ["e\u{301}te\u{301}!", "next", ""]);
+        assert_eq!(window.content().cell(0, 2).text, "e\u{301}");
+    }
+
+    #[test]
+    fn write_drops_lines_past_the_bottom() {
+        let _colors = isolated_colors(true);
+        let mut window = window(4, 14, "");
+        window.write("one\ntwo\nthree");
+
+        assert_eq!(window.content().lines(), ["one", "two"]);
+    }
+
+    #[test]
+    fn title_is_drawn_on_the_border() {
+        let _colors = isolated_colors(true);
+        let mut window = window(4, 16, "[[EFFECT_UNDERLINE]]Cpu[[EFFECT_UNDERLINE]] 1");
+        window.write("50%");
+
+        assert_eq!(
+            screen(&window),
+            [
+                "┌─ Cpu 1 ──────┐",
+                "│ 50%          │",
+                "│              │",
+                "└──────────────┘",
+            ]
+        );
+        assert_eq!(
+            window.frame().cell(0, 3).attr,
+            ncurses::A_BOLD() | ncurses::A_UNDERLINE()
+        );
+        assert_eq!(window.frame().cell(0, 7).attr, ncurses::A_BOLD());
+        assert_eq!(window.frame().cell(0, 8).attr, ncurses::A_NORMAL());
+    }
+
+    #[test]
+    fn title_is_cut_to_the_border() {
+        let _colors = isolated_colors(true);
+        let mut window = window(4, 12, "[[EFFECT_BOLD]]Processes[[EFFECT_BOLD]] (tree)");
+        window.write("");
+
+        assert_eq!(screen(&window)[0], "┌─ Proces ─┐");
+    }
+
+    #[test]
+    fn ansi_lines() {
+        let _colors = isolated_colors(true);
+        let mut buffer = CellBuffer::new(2, 6);
+        buffer.add_str("a");
+        buffer.set_attr(color_pair(COLOR_RED, -1) | ncurses::A_BOLD());
+        buffer.add_str("b");
+        buffer.set_attr(color_pair(208, COLOR_BLUE));
+        buffer.add_str("c ");
+
+        assert_eq!(
+            buffer.ansi_lines(),
+            ["a\x1b[0;1;31mb\x1b[0;38;5;208;44mc \x1b[0m", ""]
+        );
+    }
+
+    #[test]
+    fn border_styles() {
+        let _colors = isolated_colors(true);
+        let mut window = Window::with_backend(
+            layout::Rect {
+                x: 0,
+                y: 0,
+                width: 14,
+                height: 4,
+            },
+            CellBuffer::new(4, 14),
+            CellBuffer::new(2, 10),
+            WindowStyle {
+                border_style: theme::BorderStyle::Rounded,
+                ..style()
+            },
+            String::from("1"),
+        );
+        window.set_title(String::from("Memory"));
+        window.set_border_color(ncurses::A_DIM());
+
+        assert_eq!(
+            screen(&window),
+            [
+                "╭─ Memory ───╮",
+                "│            │",
+                "│            │",
+                "╰────────────╯"
+            ]
+        );
+        assert_eq!(window.frame().cell(3, 0).attr, ncurses::A_DIM());
     }
 }